@@ -1,9 +1,25 @@
 use iced::{Alignment, Color, Element, Length, Task, Theme,
-           widget::{column, Text,text, button, text_input, row, scrollable, container, Space}, Settings, Renderer};
-use crate::host_manager::{Line, write_hosts_entries_to_file, Entry};
-use crate::{host_manager, profile_view,db_manager};
+           widget::{column, Text,text, button, checkbox, text_input, row, scrollable, container, pick_list, Space}, Settings, Renderer, Subscription, keyboard};
+use iced::futures::{SinkExt, StreamExt, Stream};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::host_manager::{Line, Entry, MergeConflict, MergeResolution};
+use crate::{host_manager, profile_view, drift_view, db_manager};
 use crate::db_manager::{update_profile, Profile};
-use crate::dns_lookup::resolve_hostname_with_specific_dns;
+use crate::dns_lookup::{lookup_details_with_specific_dns, LookupDetails, reverse_lookup_with_specific_dns, system_dns_servers, KNOWN_DNS_SERVERS};
+use crate::bundle::{build_bundle, parse_bundle, ProfileBundle};
+use crate::db_manager::{HostMatch, ImportAction};
+use crate::exporters::{export_profile, ExportFormat};
+use crate::sources::{fingerprint, lines_with_sources, load_source, resolve_sources, update_cache, PendingSource, ProfileSource, SourceData, SourceFormat};
+use crate::health_check::{check_entries, parse_ports, summarize, HealthResult, HealthStatus};
+use crate::drift_report::{build_drift_report, render_report, DriftReport, DriftStatus, ReportFormat};
+use crate::entry_filter::{EntryFilter, IpVersionFilter, StatusFilter};
+use crate::apply::{apply_and_commit, apply_profile, ApplyError, ApplyOptions, ApplyReport, ProfileUpdate};
+use crate::hooks::{HookRun, HookSettings, DEFAULT_HOOK_TIMEOUT_SECS};
+use crate::validation::{find_issues, fix_duplicates, is_protected_localhost, Issue, IssueKind};
 
 // Enum for the current view
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -11,27 +27,67 @@ pub enum View {
     #[default]
     Main,
     Profiles,
+    Drift,
 }
 #[derive(Debug, Clone)]
 pub enum Message {
     InputIpChanged(String),
     InputHostnameChanged(String),
     ManualAddButtonPressed,
+    ReverseLookupButtonPressed,
+    ReverseLookupResult(Result<Vec<String>, String>),
+    ReverseCandidateSelected(String),
 
     InputChanged(String),
     InputDNSChanged(String),
+    DnsServerSelected(String),
+    ToggleFavouriteDnsServer,
+    LoadDnsSettings,
+    LoadDnsSettingsResult(Result<DnsSettings, String>),
     DnsLookupButtonPressed,
-    DnsLookupResult(Result<String, String>),
-
+    DnsLookupResult(Result<LookupDetails, String>),
+    UseLookupName(String),
+    CloseLookupDetails,
+
+    EntriesPageChanged(usize),
+    FilterQueryChanged(String),
+    FilterRegexToggled(bool),
+    FilterStatusSelected(StatusFilter),
+    FilterIpVersionSelected(IpVersionFilter),
+    ClearFilter,
+    FixDuplicates,
     DeleteEntry(usize),
     EditEntry(usize),
+    MoveLineUp(usize),
+    MoveLineDown(usize),
+    NewSectionNameChanged(String),
+    AddSectionButtonPressed,
+    ToggleSectionCollapsed(String),
+    ModifiersChanged(keyboard::Modifiers),
+    LineSelectionToggled(usize, bool),
+    SelectVisibleLines,
+    ClearSelection,
+    BulkSetEnabled(bool),
+    BulkDelete,
+    BulkIpChanged(String),
+    BulkChangeIp,
+    TransferTargetSelected(ProfileChoice),
+    BulkCopyToProfile,
+    BulkMoveToProfile,
+    TransferEntry(usize),
+    CancelTransfer,
+    CopyEntryToProfile,
+    MoveEntryToProfile,
+    EntriesTransferred(Result<TransferOutcome, ApplyError>),
     EditIpChanged(String),
     EditHostnameChanged(String),
     SaveEditedEntry,
     CancelEdit,
-    SaveSuccess,
+    HostsApplied(Result<ApplyReport, ApplyError>),
     SaveError(String),
-    ProfileSelected(Profile),
+    RetryApply,
+    // Solo l'id: la vista non copia le righe del profilo a ogni disegno
+    ProfileSelected(String),
     NewProfileNameChanged(String),
     CreateProfileButtonPressed,
     LoadProfiles,
@@ -41,19 +97,189 @@ pub enum Message {
     DeleteProfile(String),
     UpdateDatabaseResult(Result<(), String>),
 
+    ExportFormatSelected(ExportFormat),
     ExportProfilesButtonPressed,
     ImportProfilesButtonPressed,
     ExportProfilesResult(Result<(), String>),
     ImportProfilesResult(Result<(), String>),
+    BundleImported(Result<Option<ApplyReport>, ApplyError>),
+    ImportHostsFileButtonPressed,
+    ImportHostsFileLoaded(Result<HostsImport, String>),
+    HostsImportNameChanged(String),
+    HostsImportTargetSelected(ImportTarget),
+    MergeResolutionSelected(usize, MergeResolution),
+    ImportProfileParsed(Result<Profile, String>),
+    ConfirmHostsImport,
+    CancelHostsImport,
+    ExportBundleButtonPressed,
+    ImportBundleButtonPressed,
+    ImportBundleLoaded(Result<ProfileBundle, String>),
+    BundleConflictChoiceSelected(usize, ConflictChoice),
+    BundleRenameChanged(usize, String),
+    BundleImportSettingsToggled(bool),
+    ConfirmBundleImport,
+    CancelBundleImport,
+
+    DriftReportButtonPressed,
+    DriftReportResult(Result<DriftReport, String>),
+    ExportDriftReport(ReportFormat),
+    ExportDriftReportResult(Result<(), String>),
+
+    HealthPortsChanged(String),
+    HealthPeriodicToggled(bool),
+    RunHealthCheck,
+    HealthCheckResult(HashMap<String, HealthResult>),
+    LoadHealthPortsResult(Result<Option<String>, String>),
+    StagedEditsLoaded(Result<Option<String>, String>),
+    StagedEditsToggled(bool),
+    FlushDnsCacheLoaded(Result<Option<String>, String>),
+    FlushDnsCacheToggled(bool),
+    HookSettingsLoaded(Result<HookSettings, String>),
+    HostsNotAppliedLoaded(Result<Option<String>, String>),
+    PreApplyHookChanged(String),
+    PostApplyHookChanged(String),
+    HookTimeoutChanged(String),
+    AbortOnPreHookFailureToggled(bool),
+    SaveHookSettings,
+    ClearHookLog,
+    ApplyPendingChanges,
+    DiscardPendingChanges,
+    PendingChangesDiscarded(Result<Vec<Line>, String>),
+
+    LoadSources,
+    LoadSourcesResult(Result<Vec<ProfileSource>, String>),
+    RefreshSources,
+    SourcesRefreshed(Vec<(String, SourceData)>),
+    SourceFormatSelected(SourceFormat),
+    AddSourceButtonPressed,
+    RemoveSource(String),
+    EditProfile(String),
+    GlobalSearchChanged(String),
+    GlobalSearchButtonPressed,
+    GlobalSearchResult(Result<Vec<HostMatch>, String>),
+    JumpToEntry(String, usize),
+    SourceChangeResult(Result<(), String>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DnsSettings {
+    pub system_servers: Vec<String>,
+    pub favourite_servers: Vec<String>,
+    pub last_used: Option<String>,
+}
+
+// Destinazione di un import da file hosts: un nuovo profilo o il merge in uno esistente
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportTarget {
+    NewProfile,
+    Existing { id: String, name: String },
+}
+
+impl fmt::Display for ImportTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportTarget::NewProfile => write!(f, "Nuovo profilo"),
+            ImportTarget::Existing { name, .. } => write!(f, "Unisci in '{}'", name),
+        }
+    }
+}
+
+// Profilo di destinazione per copia e spostamento dei record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileChoice {
+    pub id: String,
+    pub name: String,
+}
+
+impl fmt::Display for ProfileChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+// Esito di una copia o di uno spostamento: righe aggiornate del profilo di destinazione
+// ed esito della scrittura del file hosts, se il trasferimento riguardava il profilo attivo
+#[derive(Debug, Clone)]
+pub struct TransferOutcome {
+    pub target_id: String,
+    pub target_hosts: Vec<Line>,
+    pub report: Option<ApplyReport>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct HostsImport {
+    pub profile_name: String,
+    pub lines: Vec<Line>,
+    pub target: ImportTarget,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl HostsImport {
+    // Record nuovi che l'import aggiungerà, esclusi gli hostname in conflitto
+    pub fn lines_to_add(&self, state: &MyApp) -> Vec<Line> {
+        match &self.target {
+            ImportTarget::NewProfile => self.lines.clone(),
+            ImportTarget::Existing { id, .. } => match state.profile_hosts(id) {
+                Some(hosts) => host_manager::new_entries_for_merge(hosts, &self.lines),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    fn set_target(&mut self, target: ImportTarget, state: &MyApp) {
+        self.conflicts = match &target {
+            ImportTarget::NewProfile => Vec::new(),
+            ImportTarget::Existing { id, .. } => match state.profile_hosts(id) {
+                Some(hosts) => host_manager::find_merge_conflicts(hosts, &self.lines),
+                None => Vec::new(),
+            },
+        };
+        self.target = target;
+    }
+}
+
+// Scelta per un profilo del bundle il cui nome esiste già
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictChoice {
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+impl ConflictChoice {
+    pub const ALL: [ConflictChoice; 3] = [ConflictChoice::Skip, ConflictChoice::Overwrite, ConflictChoice::Rename];
+}
+
+impl fmt::Display for ConflictChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictChoice::Skip => write!(f, "Salta"),
+            ConflictChoice::Overwrite => write!(f, "Sovrascrivi"),
+            ConflictChoice::Rename => write!(f, "Rinomina"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleImport {
+    pub bundle: ProfileBundle,
+    pub actions: Vec<ImportAction>,
+    pub import_settings: bool,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct MyApp {
     pub input_text: String,
     pub input_text_dns: String,
+    pub dns_settings: DnsSettings,
     pub input_ip: String,
     pub input_hostname: String,
-    pub file_lines: Vec<Line>,
+    pub reverse_candidates: Vec<String>,
+    pub lookup_details: Option<LookupDetails>,
+    pub file_lines: Arc<Vec<Line>>,
+    pub entries_page: usize,
+    pub entry_filter: EntryFilter,
     pub editing_index: Option<usize>,
     pub editing_ip: String,
     pub editing_hostname: String,
@@ -61,15 +287,171 @@ pub struct MyApp {
     pub success_message: Option<String>,
     pub profiles: Vec<Profile>,
     pub selected_profile: Option<Profile>,
+    // Profilo mostrato e modificato nella vista principale, le cui righe sono in `file_lines`.
+    // Può essere diverso da `selected_profile`, che è quello attivo nel file hosts.
+    pub editing_profile_id: Option<String>,
     pub new_profile_name: String,
+    pub hosts_import: Option<HostsImport>,
+    pub bundle_import: Option<BundleImport>,
+    pub export_format: ExportFormat,
     pub view: View,
+    pub drift_report: Option<DriftReport>,
+    pub health_ports: String,
+    pub health_periodic: bool,
+    pub health_running: bool,
+    pub health_results: HashMap<String, HealthResult>,
+    pub sources: Vec<ProfileSource>,
+    pub source_cache: HashMap<String, SourceData>,
+    pub new_source_format: SourceFormat,
+    pub sources_refreshing: bool,
+    // Falso finché le sorgenti non sono state caricate la prima volta dopo l'avvio
+    pub sources_loaded: bool,
+    pub global_search_query: String,
+    pub global_search_results: Vec<HostMatch>,
+    pub highlighted_index: Option<usize>,
+    pub new_section_name: String,
+    pub collapsed_sections: HashSet<String>,
+    pub selected_lines: BTreeSet<usize>,
+    pub last_selected_line: Option<usize>,
+    pub modifiers: keyboard::Modifiers,
+    pub bulk_ip: String,
+    pub transfer_target: Option<ProfileChoice>,
+    pub transferring_index: Option<usize>,
+    pub staged_edits: bool,
+    pub pending_changes: usize,
+    pub flush_dns_cache: bool,
+    pub hook_settings: HookSettings,
+    // Hook in modifica nel form: passano in `hook_settings`, usati dalle applicazioni, solo con "Salva hook"
+    pub hook_draft: HookSettings,
+    pub hook_timeout_input: String,
+    pub hook_log: Vec<HookRun>,
+    // Profilo la cui ultima applicazione è fallita, salvato nel database per ritrovarlo al riavvio
+    pub hosts_not_applied: Option<String>,
+    // Problemi dei record di `file_lines`, aggiornati in `update` quando `issues_stale` è vero
+    pub issues: HashMap<usize, Issue>,
+    pub issues_stale: bool,
+}
+
+
 
+impl Default for MyApp {
+    fn default() -> MyApp {
+        MyApp {
+            input_text: String::new(),
+            input_text_dns: String::new(),
+            dns_settings: DnsSettings::default(),
+            input_ip: String::new(),
+            input_hostname: String::new(),
+            reverse_candidates: Vec::new(),
+            lookup_details: None,
+            file_lines: Arc::new(Vec::new()),
+            entries_page: 0,
+            entry_filter: EntryFilter::default(),
+            editing_index: None,
+            editing_ip: String::new(),
+            editing_hostname: String::new(),
+            error_message: None,
+            success_message: None,
+            profiles: Vec::new(),
+            selected_profile: None,
+            editing_profile_id: None,
+            new_profile_name: String::new(),
+            hosts_import: None,
+            bundle_import: None,
+            export_format: ExportFormat::default(),
+            view: View::Main,
+            drift_report: None,
+            health_ports: crate::health_check::DEFAULT_PORTS.to_string(),
+            health_periodic: false,
+            health_running: false,
+            health_results: HashMap::new(),
+            sources: Vec::new(),
+            source_cache: HashMap::new(),
+            new_source_format: SourceFormat::default(),
+            sources_refreshing: false,
+            sources_loaded: false,
+            global_search_query: String::new(),
+            global_search_results: Vec::new(),
+            highlighted_index: None,
+            new_section_name: String::new(),
+            collapsed_sections: HashSet::new(),
+            selected_lines: BTreeSet::new(),
+            last_selected_line: None,
+            modifiers: keyboard::Modifiers::default(),
+            bulk_ip: String::new(),
+            transfer_target: None,
+            transferring_index: None,
+            staged_edits: false,
+            pending_changes: 0,
+            flush_dns_cache: true,
+            hook_settings: HookSettings::default(),
+            hook_draft: HookSettings::default(),
+            hook_timeout_input: DEFAULT_HOOK_TIMEOUT_SECS.to_string(),
+            hook_log: Vec::new(),
+            hosts_not_applied: None,
+            issues: HashMap::new(),
+            issues_stale: false,
+        }
+    }
 }
 
+impl MyApp {
+    // Le righe del profilo in modifica vivono solo in `file_lines`: la sua copia in `profiles`
+    // viene riallineata quando si passa a un altro profilo o al prossimo caricamento dal database.
+    pub fn profile_hosts(&self, profile_id: &str) -> Option<&[Line]> {
+        if self.editing_profile_id.as_deref() == Some(profile_id) {
+            return Some(&self.file_lines);
+        }
+        self.profiles.iter().find(|p| p.id == profile_id).map(|p| p.hosts.as_slice())
+    }
+
+    pub fn editing_profile(&self) -> Option<&Profile> {
+        let id = self.editing_profile_id.as_ref()?;
+        self.profiles.iter().find(|p| &p.id == id)
+    }
+
+    pub fn is_editing_active(&self) -> bool {
+        self.editing_profile_id.is_some()
+            && self.editing_profile_id.as_ref() == self.selected_profile.as_ref().map(|p| &p.id)
+    }
+
+    // Righe del profilo attivo da scrivere nel file hosts, senza copie se è quello in modifica
+    pub fn active_lines(&self) -> Option<Arc<Vec<Line>>> {
+        let active = self.selected_profile.as_ref()?;
+        if self.is_editing_active() {
+            return Some(self.file_lines.clone());
+        }
+        self.profile_hosts(&active.id).map(|hosts| Arc::new(hosts.to_vec()))
+    }
+
+    // Copia aggiornata di un profilo, per operazioni una tantum come export e report
+    pub fn profile_snapshot(&self, profile_id: &str) -> Option<Profile> {
+        let profile = self.profiles.iter().find(|p| p.id == profile_id)?;
+        Some(Profile {
+            hosts: self.profile_hosts(profile_id)?.to_vec(),
+            ..profile.clone()
+        })
+    }
 
+    pub fn editing_profile_snapshot(&self) -> Option<Profile> {
+        self.profile_snapshot(self.editing_profile_id.as_ref()?)
+    }
+}
 
 fn update(state: &mut MyApp, message: Message) -> Task<Message> {
-    if !matches!(message, Message::SaveSuccess | Message::SaveError(_)) {
+    let task = handle_message(state, message);
+    // I problemi dei record si ricalcolano solo quando cambiano le righe, non a ogni render:
+    // con profili da 100k righe il controllo pesa troppo per ogni tasto premuto
+    if state.issues_stale {
+        state.issues = find_issues(&state.file_lines);
+        state.issues_stale = false;
+    }
+    task
+}
+
+fn handle_message(state: &mut MyApp, message: Message) -> Task<Message> {
+    if !matches!(message, Message::HostsApplied(_) | Message::SaveError(_) | Message::RunHealthCheck | Message::HealthCheckResult(_)
+        | Message::RefreshSources | Message::SourcesRefreshed(_) | Message::ModifiersChanged(_)) {
         state.error_message = None;
         state.success_message = None;
     }
@@ -83,6 +465,52 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
         Message::InputDNSChanged(value) => {
             state.input_text_dns = value;
         }
+        Message::DnsServerSelected(server) => {
+            state.input_text_dns = server;
+        }
+        Message::ToggleFavouriteDnsServer => {
+            let server = state.input_text_dns.trim().to_string();
+            if server.is_empty() {
+                state.error_message = Some("DNS server is required.".to_string());
+                return Task::none();
+            }
+
+            let favourites = &mut state.dns_settings.favourite_servers;
+            if let Some(pos) = favourites.iter().position(|s| *s == server) {
+                favourites.remove(pos);
+            } else {
+                favourites.push(server);
+            }
+            let favourites_to_save = favourites.clone();
+
+            return Task::perform(async move {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                db_manager::set_favourite_dns_servers(&conn, &favourites_to_save)
+                    .map_err(|e| e.to_string())
+            }, Message::UpdateDatabaseResult);
+        }
+        Message::LoadDnsSettings => {
+            return Task::perform(async {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                Ok(DnsSettings {
+                    system_servers: system_dns_servers(),
+                    favourite_servers: db_manager::get_favourite_dns_servers(&conn).map_err(|e| e.to_string())?,
+                    last_used: db_manager::get_setting(&conn, "last_dns_server").map_err(|e| e.to_string())?,
+                })
+            }, Message::LoadDnsSettingsResult);
+        }
+        Message::LoadDnsSettingsResult(Ok(settings)) => {
+            // Priorità: ultimo server usato, poi il primo resolver di sistema
+            if state.input_text_dns.is_empty() {
+                state.input_text_dns = settings.last_used.clone()
+                    .or_else(|| settings.system_servers.first().cloned())
+                    .unwrap_or_default();
+            }
+            state.dns_settings = settings;
+        }
+        Message::LoadDnsSettingsResult(Err(e)) => {
+            state.error_message = Some(format!("Error loading DNS settings: {}", e));
+        }
         Message::InputIpChanged(value) => {
             state.input_ip = value;
         }
@@ -100,6 +528,7 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
 
             state.input_ip.clear();
             state.input_hostname.clear();
+            state.reverse_candidates.clear();
 
             let new_entry = Line::Entry(Entry {
                 ip,
@@ -107,34 +536,52 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
                 enabled:true,
                 comment: None,
             });
-            state.file_lines.push(new_entry);
-
-            if let Some(profile) = &mut state.selected_profile {
-                profile.hosts = state.file_lines.clone();
-                let entries_to_save = state.file_lines.clone();
-                let profile_to_update = profile.clone();
+            state.issues_stale = true;
+            Arc::make_mut(&mut state.file_lines).push(new_entry);
+            state.entries_page = page_count(state.file_lines.len()) - 1;
 
-                return Task::batch(vec![
-                    Task::perform(async move {
-                        write_hosts_entries_to_file(&entries_to_save)
-                            .map_err(|e| e.to_string())
-                    }, |result| {
-                        match result {
-                            Ok(_) => Message::SaveSuccess,
-                            Err(e) => Message::SaveError(e),
-                        }
-                    }),
-                    Task::perform(async move {
-                        let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
-                        update_profile(&conn, &profile_to_update)
-                            .map_err(|e| e.to_string())
-                    }, Message::UpdateDatabaseResult),
-                ]);
+            if state.editing_profile_id.is_some() {
+                return save_edited_profile(state);
             } else {
                 state.error_message = Some("No Profile Selected.".to_string());
             }
 
 
+        }
+        Message::ReverseLookupButtonPressed => {
+            let ip = state.input_ip.trim().to_string();
+            let dns_server = state.input_text_dns.clone();
+            if ip.is_empty() {
+                state.error_message = Some("IP is required for reverse lookup.".to_string());
+                return Task::none();
+            }
+            state.reverse_candidates.clear();
+            state.success_message = Some("Hostname searching ...".to_string());
+
+            return Task::batch(vec![
+                remember_dns_server(&dns_server),
+                Task::perform(async move {
+                    reverse_lookup_with_specific_dns(&ip, &dns_server)
+                        .map_err(|e| e.to_string())
+                }, Message::ReverseLookupResult),
+            ]);
+        }
+        Message::ReverseLookupResult(Ok(hostnames)) => {
+            // Con un solo candidato compiliamo direttamente il campo hostname
+            if hostnames.len() == 1 {
+                state.input_hostname = hostnames[0].clone();
+                state.success_message = Some(format!("Hostname found: {}", hostnames[0]));
+            } else {
+                state.success_message = Some(format!("{} hostnames found, pick one.", hostnames.len()));
+                state.reverse_candidates = hostnames;
+            }
+        }
+        Message::ReverseLookupResult(Err(e)) => {
+            state.error_message = Some(e);
+        }
+        Message::ReverseCandidateSelected(hostname) => {
+            state.input_hostname = hostname;
+            state.reverse_candidates.clear();
         }
         Message::DnsLookupButtonPressed => {
             let hostname = state.input_text.clone();
@@ -145,13 +592,18 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
             }
             state.success_message = Some("IP searching ...".to_string());
 
-            return Task::perform(async move {
-                resolve_hostname_with_specific_dns(&hostname,&dns_server)
-                    .map_err(|e| e.to_string())
-            }, Message::DnsLookupResult);
+            return Task::batch(vec![
+                remember_dns_server(&dns_server),
+                Task::perform(async move {
+                    lookup_details_with_specific_dns(&hostname,&dns_server)
+                        .map_err(|e| e.to_string())
+                }, Message::DnsLookupResult),
+            ]);
         }
-        Message::DnsLookupResult(Ok(ip_address)) => {
+        Message::DnsLookupResult(Ok(details)) => {
+            let ip_address = details.ips[0].clone();
             state.success_message = Some(format!("IP found: {}", ip_address));
+            state.lookup_details = Some(details);
 
             let new_entry = Line::Entry(Entry {
                 ip: ip_address,
@@ -159,28 +611,11 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
                 enabled: true,
                 comment: None,
             });
-            state.file_lines.push(new_entry);
-            if let Some(profile) = &mut state.selected_profile {
-                profile.hosts = state.file_lines.clone();
-                let entries_to_save = state.file_lines.clone();
-                let profile_to_update = profile.clone();
-
-                return Task::batch(vec![
-                    Task::perform(async move {
-                        write_hosts_entries_to_file(&entries_to_save)
-                            .map_err(|e| e.to_string())
-                    }, |result| {
-                        match result {
-                            Ok(_) => Message::SaveSuccess,
-                            Err(e) => Message::SaveError(e),
-                        }
-                    }),
-                    Task::perform(async move {
-                        let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
-                        update_profile(&conn, &profile_to_update)
-                            .map_err(|e| e.to_string())
-                    }, Message::UpdateDatabaseResult),
-                ]);
+            state.issues_stale = true;
+            Arc::make_mut(&mut state.file_lines).push(new_entry);
+            state.entries_page = page_count(state.file_lines.len()) - 1;
+            if state.editing_profile_id.is_some() {
+                return save_edited_profile(state);
             } else {
                 state.error_message = Some("Profile is required.".to_string());
             }
@@ -191,32 +626,225 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
         Message::DnsLookupResult(Err(e)) => {
             state.error_message = Some(e);
         }
+        Message::UseLookupName(hostname) => {
+            if let Some(details) = &state.lookup_details {
+                state.input_ip = details.ips[0].clone();
+                state.input_hostname = hostname;
+            }
+        }
+        Message::CloseLookupDetails => {
+            state.lookup_details = None;
+        }
 
+        Message::EntriesPageChanged(page) => {
+            // La pagina viene comunque limitata nella vista, in base alle righe filtrate
+            state.entries_page = page;
+        }
+        Message::FilterQueryChanged(query) => {
+            state.entry_filter.set_query(query);
+            state.entries_page = 0;
+        }
+        Message::FilterRegexToggled(use_regex) => {
+            state.entry_filter.set_use_regex(use_regex);
+            state.entries_page = 0;
+        }
+        Message::FilterStatusSelected(status) => {
+            state.entry_filter.status = status;
+            state.entries_page = 0;
+        }
+        Message::FilterIpVersionSelected(ip_version) => {
+            state.entry_filter.ip_version = ip_version;
+            state.entries_page = 0;
+        }
+        Message::ClearFilter => {
+            state.entry_filter = EntryFilter::default();
+            state.entries_page = 0;
+        }
+        Message::FixDuplicates => {
+            state.issues_stale = true;
+            let changed = fix_duplicates(Arc::make_mut(&mut state.file_lines));
+            if changed == 0 {
+                return Task::none();
+            }
+            state.editing_index = None;
+            state.highlighted_index = None;
+            clear_selection(state);
+            state.success_message = Some(format!("Corrette {} righe duplicate o in conflitto.", changed));
+            if state.editing_profile_id.is_some() {
+                return save_edited_profile(state);
+            }
+        }
         Message::DeleteEntry(index) => {
             if index < state.file_lines.len() {
-                state.file_lines.remove(index);
-                if let Some(profile) = &mut state.selected_profile {
-                    profile.hosts = state.file_lines.clone();
-
-                    let entries_to_save = state.file_lines.clone();
-                    let profile_to_update = profile.clone();
-
-                    return Task::batch(vec![
-                        Task::perform(async move {
-                            write_hosts_entries_to_file(&entries_to_save)
-                                .map_err(|e| e.to_string())
-                        }, |result| {
-                            match result {
-                                Ok(_) => Message::SaveSuccess,
-                                Err(e) => Message::SaveError(e),
-                            }
-                        }),
-                        Task::perform(async move {
-                            let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
-                            update_profile(&conn, &profile_to_update)
-                                .map_err(|e| e.to_string())
-                        }, Message::UpdateDatabaseResult),
-                    ]);
+                state.highlighted_index = None;
+                state.editing_index = None;
+                state.transferring_index = None;
+                clear_selection(state);
+                state.issues_stale = true;
+                Arc::make_mut(&mut state.file_lines).remove(index);
+                if state.editing_profile_id.is_some() {
+                    return save_edited_profile(state);
+                }
+            }
+        }
+        Message::MoveLineUp(index) => {
+            if index > 0 && index < state.file_lines.len() {
+                return swap_lines(state, index - 1, index);
+            }
+        }
+        Message::MoveLineDown(index) => {
+            if index + 1 < state.file_lines.len() {
+                return swap_lines(state, index, index + 1);
+            }
+        }
+        Message::NewSectionNameChanged(name) => {
+            state.new_section_name = name;
+        }
+        Message::AddSectionButtonPressed => {
+            let name = state.new_section_name.trim().to_string();
+            if name.is_empty() {
+                state.error_message = Some("Il nome della sezione non può essere vuoto.".to_string());
+                return Task::none();
+            }
+            if state.file_lines.iter().any(|line| matches!(line, Line::Section(existing) if *existing == name)) {
+                state.error_message = Some(format!("Esiste già una sezione '{}'.", name));
+                return Task::none();
+            }
+            // La nuova sezione va in fondo: i record aggiunti dopo finiscono al suo interno
+            state.issues_stale = true;
+            let lines = Arc::make_mut(&mut state.file_lines);
+            lines.push(Line::Empty);
+            lines.push(Line::Section(name));
+            state.new_section_name.clear();
+            state.entries_page = page_count(state.file_lines.len()) - 1;
+            if state.editing_profile_id.is_some() {
+                return save_edited_profile(state);
+            }
+        }
+        Message::ToggleSectionCollapsed(name) => {
+            if !state.collapsed_sections.remove(&name) {
+                state.collapsed_sections.insert(name);
+            }
+        }
+        Message::ModifiersChanged(modifiers) => {
+            state.modifiers = modifiers;
+        }
+        Message::LineSelectionToggled(index, checked) => {
+            // Con shift premuto viene selezionato l'intervallo dei record visibili dall'ultimo clic
+            match state.last_selected_line {
+                Some(anchor) if state.modifiers.shift() => {
+                    let (from, to) = (anchor.min(index), anchor.max(index));
+                    let range: Vec<usize> = state.entry_filter
+                        .visible_indices(&state.file_lines, &state.collapsed_sections)
+                        .into_iter()
+                        .filter(|&i| i >= from && i <= to && is_selectable(&state.file_lines[i]))
+                        .collect();
+                    for i in range {
+                        if checked {
+                            state.selected_lines.insert(i);
+                        } else {
+                            state.selected_lines.remove(&i);
+                        }
+                    }
+                }
+                _ if checked => {
+                    if state.file_lines.get(index).is_some_and(is_selectable) {
+                        state.selected_lines.insert(index);
+                    }
+                }
+                _ => {
+                    state.selected_lines.remove(&index);
+                }
+            }
+            state.last_selected_line = Some(index);
+        }
+        Message::SelectVisibleLines => {
+            let visible = state.entry_filter.visible_indices(&state.file_lines, &state.collapsed_sections);
+            state.selected_lines.extend(visible.into_iter().filter(|&i| is_selectable(&state.file_lines[i])));
+        }
+        Message::ClearSelection => {
+            clear_selection(state);
+        }
+        Message::BulkSetEnabled(enabled) => {
+            state.issues_stale = true;
+            let lines = Arc::make_mut(&mut state.file_lines);
+            for &index in &state.selected_lines {
+                if let Some(Line::Entry(entry)) = lines.get_mut(index) {
+                    entry.enabled = enabled;
+                }
+            }
+            if state.editing_profile_id.is_some() {
+                return save_edited_profile(state);
+            }
+        }
+        Message::BulkDelete => {
+            let indices = state.selected_lines.clone();
+            remove_lines(state, &indices);
+            if state.editing_profile_id.is_some() {
+                return save_edited_profile(state);
+            }
+        }
+        Message::BulkIpChanged(ip) => {
+            state.bulk_ip = ip;
+        }
+        Message::BulkChangeIp => {
+            let ip = state.bulk_ip.trim().to_string();
+            if ip.parse::<std::net::IpAddr>().is_err() {
+                state.error_message = Some(format!("IP non valido: '{}'", ip));
+                return Task::none();
+            }
+            state.issues_stale = true;
+            let lines = Arc::make_mut(&mut state.file_lines);
+            for &index in &state.selected_lines {
+                if let Some(Line::Entry(entry)) = lines.get_mut(index) {
+                    entry.ip = ip.clone();
+                }
+            }
+            state.bulk_ip.clear();
+            if state.editing_profile_id.is_some() {
+                return save_edited_profile(state);
+            }
+        }
+        Message::TransferTargetSelected(target) => {
+            state.transfer_target = Some(target);
+        }
+        Message::BulkCopyToProfile | Message::BulkMoveToProfile => {
+            let indices = state.selected_lines.clone();
+            return transfer_lines(state, indices, matches!(message, Message::BulkMoveToProfile));
+        }
+        Message::TransferEntry(index) => {
+            state.transferring_index = Some(index);
+        }
+        Message::CancelTransfer => {
+            state.transferring_index = None;
+        }
+        Message::CopyEntryToProfile | Message::MoveEntryToProfile => {
+            if let Some(index) = state.transferring_index {
+                return transfer_lines(state, BTreeSet::from([index]), matches!(message, Message::MoveEntryToProfile));
+            }
+        }
+        Message::EntriesTransferred(result) => {
+            match result {
+                Ok(outcome) => {
+                    // Aggiorna solo la copia in memoria del profilo di destinazione
+                    if let Some(profile) = state.profiles.iter_mut().find(|p| p.id == outcome.target_id) {
+                        profile.hosts = outcome.target_hosts;
+                    }
+                    let task = match outcome.report {
+                        Some(report) => handle_message(state, Message::HostsApplied(Ok(report))),
+                        None => Task::none(),
+                    };
+                    state.success_message = Some(match state.success_message.take() {
+                        Some(applied) => format!("{} {}", outcome.message, applied),
+                        None => outcome.message,
+                    });
+                    return task;
+                }
+                Err(e) => {
+                    state.error_message = Some(format!("Errore nel trasferimento dei record: {}", apply_error_message(&e)));
+                    record_hook_runs(state, e.hook_runs);
+                    // Database e file hosts sono rimasti com'erano: si ricarica lo stato salvato
+                    return Task::perform(async {}, |_| Message::LoadProfiles);
                 }
             }
         }
@@ -235,40 +863,20 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
             state.editing_hostname = new_hostname;
         }
         Message::SaveEditedEntry => {
-            if let Some(index) = state.editing_index {
-                if let Some(Line::Entry(entry)) = state.file_lines.get_mut(index) {
-                    entry.ip = state.editing_ip.clone();
-                    entry.hostname = state.editing_hostname.clone();
-
-                    state.editing_index = None;
-                    state.editing_ip.clear();
-                    state.editing_hostname.clear();
-
-                    if let Some(profile) = &mut state.selected_profile {
-                        profile.hosts = state.file_lines.clone();
-
-                        let entries_to_save = state.file_lines.clone();
-                        let profile_to_update = profile.clone();
-
-                        return Task::batch(vec![
-                            Task::perform(async move {
-                                write_hosts_entries_to_file(&entries_to_save)
-                                    .map_err(|e| e.to_string())
-                            }, |result| {
-                                match result {
-                                    Ok(_) => Message::SaveSuccess,
-                                    Err(e) => Message::SaveError(e),
-                                }
-                            }),
-                            Task::perform(async move {
-                                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
-                                update_profile(&conn, &profile_to_update)
-                                    .map_err(|e| e.to_string())
-                            }, Message::UpdateDatabaseResult),
-                        ]);
-                    } else {
-                        state.error_message = Some("Select a profile to save changes.".to_string());
-                    }
+            state.issues_stale = true;
+            if let Some(index) = state.editing_index
+                && let Some(Line::Entry(entry)) = Arc::make_mut(&mut state.file_lines).get_mut(index) {
+                entry.ip = state.editing_ip.clone();
+                entry.hostname = state.editing_hostname.clone();
+
+                state.editing_index = None;
+                state.editing_ip.clear();
+                state.editing_hostname.clear();
+
+                if state.editing_profile_id.is_some() {
+                    return save_edited_profile(state);
+                } else {
+                    state.error_message = Some("Select a profile to save changes.".to_string());
                 }
             }
         }
@@ -279,9 +887,37 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
             state.editing_hostname = String::new();
             println!("Modifica annullata.");
         }
-        Message::SaveSuccess => {
-            state.error_message = None;
-            state.success_message = Some(String::from("Saved successfully."));
+        Message::HostsApplied(result) => {
+            let (hook_runs, not_applied) = match result {
+                Ok(report) => {
+                    state.error_message = None;
+                    state.success_message = Some(match report.flush_summary {
+                        Some(summary) => format!("Saved successfully. Cache DNS: {}", summary),
+                        None => String::from("Saved successfully."),
+                    });
+                    if report.hook_runs.iter().any(|run| !run.success) {
+                        state.error_message = Some("Un hook non è terminato correttamente, vedi il log degli hook.".to_string());
+                    }
+                    (report.hook_runs, None)
+                }
+                Err(e) => {
+                    // Le modifiche restano in memoria: "Riprova" salva di nuovo il profilo attivo
+                    // e riscrive il file hosts in un'unica operazione
+                    state.error_message = Some(apply_error_message(&e));
+                    (e.hook_runs, state.selected_profile.as_ref().map(|p| p.id.clone()))
+                }
+            };
+            record_hook_runs(state, hook_runs);
+            return set_hosts_not_applied(state, not_applied);
+        }
+        Message::HostsNotAppliedLoaded(Ok(profile_id)) => {
+            state.hosts_not_applied = profile_id;
+        }
+        Message::HostsNotAppliedLoaded(Err(e)) => {
+            state.error_message = Some(format!("Error loading apply state: {}", e));
+        }
+        Message::RetryApply => {
+            return apply_active_profile(state, true, true);
         }
         Message::SaveError(e) => {
             state.error_message = Some(e);
@@ -319,52 +955,56 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
 
                 let active_profile = state.profiles.iter().find(|p| p.is_active).cloned();
 
+                clear_selection(state);
                 if let Some(profile) = active_profile {
                     state.selected_profile = Some(profile.clone());
-                    state.file_lines = profile.hosts.clone();
                     state.success_message = Some(format!("Profile loaded: {}", profile.name));
                 } else {
                     state.selected_profile = state.profiles.first().cloned();
                     if let Some(profile) = &state.selected_profile {
-                        state.file_lines = profile.hosts.clone();
                         state.success_message = Some(format!("Default profile loaded: {}", profile.name));
                     }
                 }
+
+                // Resta aperto il profilo in modifica, se esiste ancora, altrimenti quello attivo.
+                // Con modifiche in sospeso le righe in memoria non vengono sostituite.
+                if state.pending_changes > 0
+                    && let Some(id) = &state.editing_profile_id
+                    && state.profiles.iter().any(|p| &p.id == id) {
+                    return Task::none();
+                }
+                state.pending_changes = 0;
+                let editing = state.editing_profile_id
+                    .as_ref()
+                    .and_then(|id| state.profiles.iter().find(|p| &p.id == id))
+                    .or(state.selected_profile.as_ref());
+                if let Some(profile) = editing {
+                    state.editing_profile_id = Some(profile.id.clone());
+                    state.issues_stale = true;
+                    state.file_lines = Arc::new(profile.hosts.clone());
+                }
             }
         }
         Message::LoadProfilesResult(Err(e)) => {
             state.error_message = Some(e);
         }
 
-        Message::ProfileSelected(profile) => {
-            state.selected_profile = Some(profile.clone());
-
-            state.file_lines = profile.hosts.clone();
-
-            let entries_to_save = state.file_lines.clone();
-            let profile_id_to_activate = profile.id.clone();
+        Message::ProfileSelected(profile_id) => {
+            // Attivare un profilo lo apre anche in modifica; se era già aperto
+            // valgono le sue righe correnti
+            if let Err(e) = open_profile(state, &profile_id) {
+                state.error_message = Some(e);
+                return Task::none();
+            }
+            for p in state.profiles.iter_mut() {
+                p.is_active = p.id == profile_id;
+            }
+            state.selected_profile = state.profiles.iter().find(|p| p.id == profile_id).cloned();
 
-            return Task::batch(vec![
-                Task::perform(async move {
-                    write_hosts_entries_to_file(&entries_to_save)
-                        .map_err(|e| e.to_string())
-                }, |result| {
-                    match result {
-                        Ok(_) => Message::SaveSuccess,
-                        Err(e) => Message::SaveError(e),
-                    }
-                }),
-                Task::perform(async move {
-                    let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
-                    db_manager::set_active_profile(&conn, &profile_id_to_activate)
-                        .map_err(|e| e.to_string())
-                }, |result| {
-                    match result {
-                        Ok(_) => Message::LoadProfiles,
-                        Err(e) => Message::SaveError(format!("Errore nell'attivazione del profilo: {}", e)),
-                    }
-                })
-            ]);
+            // Attivare il profilo applica anche le sue modifiche in sospeso
+            let save_hosts = state.pending_changes > 0;
+            state.pending_changes = 0;
+            return apply_active_profile(state, save_hosts, true);
         }
         Message::NewProfileNameChanged(name) => {
             state.new_profile_name = name;
@@ -407,19 +1047,60 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
         Message::ShowProfilesView => {
             state.view = View::Profiles;
         }
-        Message::DeleteProfile(profile_id) => {
-            if let Some(selected_profile) = &state.selected_profile {
-                if selected_profile.id == profile_id && selected_profile.name == "Default" {
-                    state.error_message = Some("Impossibile eliminare il profilo 'Default'.".to_string());
-                    return Task::none();
+        Message::EditProfile(profile_id) => {
+            if let Err(e) = open_profile(state, &profile_id) {
+                state.error_message = Some(e);
+                return Task::none();
+            }
+            state.view = View::Main;
+        }
+        Message::GlobalSearchChanged(query) => {
+            state.global_search_query = query;
+        }
+        Message::GlobalSearchButtonPressed => {
+            let query = state.global_search_query.trim().to_string();
+            if query.is_empty() {
+                state.global_search_results.clear();
+                return Task::none();
+            }
+            return Task::perform(async move {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                db_manager::search_hosts(&conn, &query).map_err(|e| e.to_string())
+            }, Message::GlobalSearchResult);
+        }
+        Message::GlobalSearchResult(result) => {
+            match result {
+                Ok(results) => {
+                    if results.is_empty() {
+                        state.success_message = Some("Nessun profilo contiene il testo cercato.".to_string());
+                    }
+                    state.global_search_results = results;
                 }
+                Err(e) => state.error_message = Some(format!("Errore nella ricerca: {}", e)),
+            }
+        }
+        Message::JumpToEntry(profile_id, index) => {
+            // Il profilo viene solo aperto in modifica, senza attivarlo
+            if let Err(e) = open_profile(state, &profile_id) {
+                state.error_message = Some(e);
+                return Task::none();
+            }
+            state.entry_filter = EntryFilter::default();
+            state.entries_page = index / ENTRIES_PAGE_SIZE;
+            state.highlighted_index = Some(index);
+            state.view = View::Main;
+        }
+        Message::DeleteProfile(profile_id) => {
+            if let Some(selected_profile) = &state.selected_profile
+                && selected_profile.id == profile_id && selected_profile.name == "Default" {
+                state.error_message = Some("Impossibile eliminare il profilo 'Default'.".to_string());
+                return Task::none();
             }
 
-            if let Some(selected_profile) = &state.selected_profile {
-                if selected_profile.id == profile_id {
-                    state.error_message = Some("Impossibile eliminare il profilo attivo. Seleziona un altro profilo prima di procedere.".to_string());
-                    return Task::none();
-                }
+            if let Some(selected_profile) = &state.selected_profile
+                && selected_profile.id == profile_id {
+                state.error_message = Some("Impossibile eliminare il profilo attivo. Seleziona un altro profilo prima di procedere.".to_string());
+                return Task::none();
             }
 
             state.success_message = Some("Eliminazione del profilo...".to_string());
@@ -441,30 +1122,31 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
             state.error_message = Some(format!("Database update error: {}", e));
         }
 
+        Message::ExportFormatSelected(format) => {
+            state.export_format = format;
+        }
         Message::ExportProfilesButtonPressed => {
-            if let Some(profile) = &state.selected_profile {
-                let profile_to_export = profile.clone();
+            if let Some(profile_to_export) = state.editing_profile_snapshot() {
+                let format = state.export_format;
                 state.success_message = Some("Apertura finestra di dialogo...".to_string());
 
                 return Task::perform(async move {
+                    let data = export_profile(&profile_to_export, format)?;
                     let file_path = rfd::AsyncFileDialog::new()
-                        .add_filter("JSON Profile", &["json"])
-                        .set_file_name(&format!("{}.json", profile_to_export.name))
+                        .add_filter(format.to_string(), &[format.extension()])
+                        .set_file_name(format.file_name(&profile_to_export.name))
                         .save_file()
                         .await;
 
                     if let Some(file) = file_path {
-                        let json_data = serde_json::to_string_pretty(&profile_to_export)
-                            .map_err(|e| format!("Errore di serializzazione: {}", e))?;
-
-                        std::fs::write(file.path(), json_data)
+                        std::fs::write(file.path(), data)
                             .map_err(|e| format!("Errore di scrittura del file: {}", e))?;
 
                         Ok(())
                     } else {
                         Err("Operazione di esportazione annullata.".to_string())
                     }
-                }, |result| Message::ExportProfilesResult(result));
+                }, Message::ExportProfilesResult);
             } else {
                 state.error_message = Some("Seleziona un profilo da esportare.".to_string());
             }
@@ -483,47 +1165,683 @@ fn update(state: &mut MyApp, message: Message) -> Task<Message> {
                     let json_data = std::fs::read_to_string(file.path())
                         .map_err(|e| format!("Errore di lettura del file: {}", e))?;
 
-                    let imported_profile: Profile = serde_json::from_str(&json_data)
-                        .map_err(|e| format!("Errore di deserializzazione: {}", e))?;
-
-                    let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
-                    db_manager::import_profile(&conn, &imported_profile)
-                        .map_err(|e| format!("Errore di importazione: {}", e))?;
-
-                    Ok(())
+                    serde_json::from_str(&json_data)
+                        .map_err(|e| format!("Errore di deserializzazione: {}", e))
                 } else {
                     Err("Operazione di importazione annullata.".to_string())
                 }
-            }, |result| Message::ImportProfilesResult(result));
-        }
-        Message::ExportProfilesResult(Ok(_)) => {
-            state.success_message = Some("Profilo esportato con successo!".to_string());
-        }
-        Message::ExportProfilesResult(Err(e)) => {
-            state.error_message = Some(e);
+            }, Message::ImportProfileParsed);
         }
+        Message::ImportProfileParsed(Ok(imported_profile)) => {
+            // Con un nome già esistente si propone il merge invece di rifiutare l'import
+            if let Some(existing) = state.profiles.iter().find(|p| p.name == imported_profile.name) {
+                let mut import = HostsImport {
+                    profile_name: imported_profile.name.clone(),
+                    lines: imported_profile.hosts,
+                    target: ImportTarget::NewProfile,
+                    conflicts: Vec::new(),
+                };
+                import.set_target(ImportTarget::Existing {
+                    id: existing.id.clone(),
+                    name: existing.name.clone(),
+                }, state);
+                state.success_message = Some(format!("Il profilo '{}' esiste già: scegli come unirlo.", existing.name));
+                state.hosts_import = Some(import);
+                return Task::none();
+            }
 
-        Message::ImportProfilesResult(Ok(_)) => {
-            state.success_message = Some("Profilo importato con successo!".to_string());
-            let _ = Task::perform(async { Message::LoadProfiles }, |msg| msg);
+            return Task::perform(async move {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                db_manager::import_profile(&conn, &imported_profile)
+                    .map_err(|e| format!("Errore di importazione: {}", e))
+            }, Message::ImportProfilesResult);
         }
-        Message::ImportProfilesResult(Err(e)) => {
+        Message::ImportProfileParsed(Err(e)) => {
             state.error_message = Some(e);
         }
+        Message::ExportBundleButtonPressed => {
+            state.success_message = Some("Apertura finestra di dialogo...".to_string());
 
-    }
+            return Task::perform(async {
+                let bundle = build_bundle()?;
+                let json_data = serde_json::to_string_pretty(&bundle)
+                    .map_err(|e| format!("Errore di serializzazione: {}", e))?;
 
-    Task::none()
-}
-pub fn view(state: &MyApp) -> Element<Message> {
-    match state.view {
+                let file_path = rfd::AsyncFileDialog::new()
+                    .add_filter("Hosts manager bundle", &["json"])
+                    .set_file_name("hosts-manager-bundle.json")
+                    .save_file()
+                    .await;
+
+                if let Some(file) = file_path {
+                    std::fs::write(file.path(), json_data)
+                        .map_err(|e| format!("Errore di scrittura del file: {}", e))?;
+                    Ok(())
+                } else {
+                    Err("Operazione di esportazione annullata.".to_string())
+                }
+            }, Message::ExportProfilesResult);
+        }
+        Message::ImportBundleButtonPressed => {
+            state.success_message = Some("Apertura finestra di dialogo...".to_string());
+
+            return Task::perform(async {
+                let file_path = rfd::AsyncFileDialog::new()
+                    .add_filter("Hosts manager bundle", &["json"])
+                    .pick_file()
+                    .await;
+
+                if let Some(file) = file_path {
+                    let json_data = std::fs::read_to_string(file.path())
+                        .map_err(|e| format!("Errore di lettura del file: {}", e))?;
+                    parse_bundle(&json_data)
+                } else {
+                    Err("Operazione di importazione annullata.".to_string())
+                }
+            }, Message::ImportBundleLoaded);
+        }
+        Message::ImportBundleLoaded(Ok(bundle)) => {
+            // I profili con nome già presente vengono saltati finché l'utente non sceglie diversamente
+            let actions = bundle.profiles
+                .iter()
+                .map(|p| if state.profiles.iter().any(|existing| existing.name == p.name) {
+                    ImportAction::Skip
+                } else {
+                    ImportAction::Insert
+                })
+                .collect();
+            state.bundle_import = Some(BundleImport {
+                bundle,
+                actions,
+                import_settings: true,
+            });
+        }
+        Message::ImportBundleLoaded(Err(e)) => {
+            state.error_message = Some(e);
+        }
+        Message::BundleConflictChoiceSelected(index, choice) => {
+            if let Some(import) = &mut state.bundle_import
+                && let Some(action) = import.actions.get_mut(index) {
+                *action = match choice {
+                    ConflictChoice::Skip => ImportAction::Skip,
+                    ConflictChoice::Overwrite => ImportAction::Overwrite,
+                    ConflictChoice::Rename => ImportAction::Rename(format!("{} (importato)", import.bundle.profiles[index].name)),
+                };
+            }
+        }
+        Message::BundleRenameChanged(index, name) => {
+            if let Some(import) = &mut state.bundle_import
+                && let Some(action) = import.actions.get_mut(index) {
+                *action = ImportAction::Rename(name);
+            }
+        }
+        Message::BundleImportSettingsToggled(enabled) => {
+            if let Some(import) = &mut state.bundle_import {
+                import.import_settings = enabled;
+            }
+        }
+        Message::CancelBundleImport => {
+            state.bundle_import = None;
+        }
+        Message::ConfirmBundleImport => {
+            let Some(import) = state.bundle_import.take() else {
+                return Task::none();
+            };
+
+            for action in &import.actions {
+                if let ImportAction::Rename(name) = action
+                    && (name.trim().is_empty() || state.profiles.iter().any(|p| p.name == name.trim())) {
+                    state.error_message = Some(format!("Il nome '{}' non è valido o esiste già.", name));
+                    state.bundle_import = Some(import);
+                    return Task::none();
+                }
+            }
+
+            // Se viene sovrascritto il profilo attivo, il file hosts va riallineato
+            let active_name = state.selected_profile.as_ref().map(|p| p.name.clone());
+            let active_overwrite = import.bundle.profiles
+                .iter()
+                .zip(import.actions.iter())
+                .find(|(p, a)| **a == ImportAction::Overwrite && Some(&p.name) == active_name.as_ref())
+                .map(|(p, _)| p.hosts.clone());
+            let active_sources = match &state.selected_profile {
+                Some(profile) => active_source_lines(&state.sources, &state.source_cache, &profile.id),
+                None => Vec::new(),
+            };
+
+            let items: Vec<_> = import.bundle.profiles
+                .into_iter()
+                .zip(import.actions)
+                .map(|(profile, action)| match action {
+                    ImportAction::Rename(name) => (profile, ImportAction::Rename(name.trim().to_string())),
+                    action => (profile, action),
+                })
+                .collect();
+            let settings = if import.import_settings { import.bundle.settings } else { Vec::new() };
+            let sources = import.bundle.sources;
+
+            let options = apply_options(state);
+
+            // L'importazione viene confermata nel database solo dopo la scrittura del file hosts
+            return Task::perform(async move {
+                let import = || -> Result<(), String> {
+                    let mut conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                    db_manager::apply_bundle_import(&mut conn, &items, &settings, &sources)
+                        .map_err(|e| format!("Errore di importazione: {}", e))
+                };
+                match active_overwrite {
+                    Some(hosts) => apply_and_commit(&lines_with_sources(&hosts, &resolve_sources(&active_sources)), &options, import).map(Some),
+                    None => import()
+                        .map(|_| None)
+                        .map_err(|message| ApplyError { message, permission_denied: false, hook_runs: Vec::new() }),
+                }
+            }, Message::BundleImported);
+        }
+        Message::ExportProfilesResult(Ok(_)) => {
+            state.success_message = Some("Profilo esportato con successo!".to_string());
+        }
+        Message::ExportProfilesResult(Err(e)) => {
+            state.error_message = Some(e);
+        }
+
+        Message::ImportProfilesResult(Ok(_)) => {
+            state.success_message = Some("Profilo importato con successo!".to_string());
+            return Task::batch(vec![
+                Task::perform(async {}, |_| Message::LoadProfiles),
+                load_settings(),
+            ]);
+        }
+        Message::ImportProfilesResult(Err(e)) => {
+            state.error_message = Some(e);
+        }
+        Message::BundleImported(Ok(report)) => {
+            let applied = match report {
+                Some(report) => handle_message(state, Message::HostsApplied(Ok(report))),
+                None => Task::none(),
+            };
+            state.success_message = Some("Profilo importato con successo!".to_string());
+            return Task::batch(vec![
+                applied,
+                Task::perform(async {}, |_| Message::LoadProfiles),
+                Task::perform(async {}, |_| Message::LoadSources),
+                load_settings(),
+            ]);
+        }
+        Message::BundleImported(Err(e)) => {
+            state.error_message = Some(apply_error_message(&e));
+            record_hook_runs(state, e.hook_runs);
+        }
+        Message::ImportHostsFileButtonPressed => {
+            state.success_message = Some("Apertura finestra di dialogo...".to_string());
+
+            return Task::perform(async {
+                let file_path = rfd::AsyncFileDialog::new()
+                    .add_filter("Hosts file", &["hosts", "txt", "conf"])
+                    .add_filter("All files", &["*"])
+                    .pick_file()
+                    .await;
+
+                if let Some(file) = file_path {
+                    let lines = host_manager::load_hosts_file(file.path())
+                        .map_err(|e| format!("Errore di lettura del file: {}", e))?;
+                    if !lines.iter().any(|l| matches!(l, Line::Entry(_))) {
+                        return Err("Il file non contiene record in formato hosts.".to_string());
+                    }
+
+                    let profile_name = file.path()
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "Importato".to_string());
+
+                    Ok(HostsImport {
+                        profile_name,
+                        lines,
+                        target: ImportTarget::NewProfile,
+                        conflicts: Vec::new(),
+                    })
+                } else {
+                    Err("Operazione di importazione annullata.".to_string())
+                }
+            }, Message::ImportHostsFileLoaded);
+        }
+        Message::ImportHostsFileLoaded(Ok(import)) => {
+            state.hosts_import = Some(import);
+        }
+        Message::ImportHostsFileLoaded(Err(e)) => {
+            state.error_message = Some(e);
+        }
+        Message::HostsImportNameChanged(name) => {
+            if let Some(import) = &mut state.hosts_import {
+                import.profile_name = name;
+            }
+        }
+        Message::HostsImportTargetSelected(target) => {
+            if let Some(mut import) = state.hosts_import.take() {
+                import.set_target(target, state);
+                state.hosts_import = Some(import);
+            }
+        }
+        Message::MergeResolutionSelected(index, resolution) => {
+            if let Some(import) = &mut state.hosts_import
+                && let Some(conflict) = import.conflicts.get_mut(index) {
+                conflict.resolution = resolution;
+            }
+        }
+        Message::CancelHostsImport => {
+            state.hosts_import = None;
+        }
+        Message::ConfirmHostsImport => {
+            let Some(import) = state.hosts_import.take() else {
+                return Task::none();
+            };
+            match import.target.clone() {
+                ImportTarget::NewProfile => {
+                    let name = import.profile_name.trim().to_string();
+                    if name.is_empty() {
+                        state.error_message = Some("Il nome del profilo non può essere vuoto.".to_string());
+                        state.hosts_import = Some(import);
+                        return Task::none();
+                    }
+                    let lines_to_add = import.lines;
+
+                    return Task::perform(async move {
+                        let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                        db_manager::create_profile(&conn, &name, &lines_to_add)
+                            .map_err(|e| format!("Errore di importazione: {}", e))
+                    }, Message::ImportProfilesResult);
+                }
+                ImportTarget::Existing { id, .. } => {
+                    let Some(mut profile) = state.profile_snapshot(&id) else {
+                        state.error_message = Some("Profilo di destinazione non trovato.".to_string());
+                        return Task::none();
+                    };
+                    let merged = host_manager::merge_lines(&profile.hosts, &import.lines, &import.conflicts);
+                    if merged == profile.hosts {
+                        state.success_message = Some("Nessuna modifica da applicare.".to_string());
+                        return Task::none();
+                    }
+                    profile.hosts = merged;
+
+                    if state.editing_profile_id.as_ref() == Some(&profile.id) {
+                        // Il merge parte dalle righe in memoria: salvandolo si applicano anche quelle in sospeso
+                        state.issues_stale = true;
+                        state.file_lines = Arc::new(profile.hosts.clone());
+                        state.pending_changes = 0;
+                        clear_selection(state);
+                    } else if let Some(existing) = state.profiles.iter_mut().find(|p| p.id == profile.id) {
+                        existing.hosts = profile.hosts.clone();
+                    }
+                    // Se il profilo è quello attivo il file hosts va riscritto subito, insieme al database
+                    if state.selected_profile.as_ref().map(|p| p.id.clone()) == Some(profile.id.clone()) {
+                        state.selected_profile = Some(profile.clone());
+                        return apply_active_profile(state, true, false);
+                    }
+                    return Task::perform(async move {
+                        let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                        update_profile(&conn, &profile)
+                            .map_err(|e| format!("Errore di importazione: {}", e))
+                    }, Message::ImportProfilesResult);
+                }
+            }
+        }
+
+        Message::DriftReportButtonPressed => {
+            if let Some(profile_to_check) = state.editing_profile_snapshot() {
+                let dns_server = state.input_text_dns.clone();
+                state.view = View::Drift;
+                state.drift_report = None;
+                state.success_message = Some("Confronto con il DNS in corso...".to_string());
+
+                return Task::batch(vec![
+                    remember_dns_server(&dns_server),
+                    Task::perform(async move {
+                        build_drift_report(&profile_to_check, &dns_server)
+                            .map_err(|e| e.to_string())
+                    }, Message::DriftReportResult),
+                ]);
+            } else {
+                state.error_message = Some("No Profile Selected.".to_string());
+            }
+        }
+        Message::DriftReportResult(Ok(report)) => {
+            let stale = report.entries.iter().filter(|e| e.status != DriftStatus::Matching).count();
+            state.success_message = Some(format!("{} record controllati, {} da verificare.", report.entries.len(), stale));
+            state.drift_report = Some(report);
+        }
+        Message::DriftReportResult(Err(e)) => {
+            state.error_message = Some(e);
+        }
+        Message::ExportDriftReport(format) => {
+            if let Some(report) = &state.drift_report {
+                let report_to_export = report.clone();
+                state.success_message = Some("Apertura finestra di dialogo...".to_string());
+
+                return Task::perform(async move {
+                    let data = render_report(&report_to_export, format)?;
+                    let file_path = rfd::AsyncFileDialog::new()
+                        .add_filter("Drift report", &[format.extension()])
+                        .set_file_name(format!("{}-drift.{}", report_to_export.profile_name, format.extension()))
+                        .save_file()
+                        .await;
+
+                    if let Some(file) = file_path {
+                        std::fs::write(file.path(), data)
+                            .map_err(|e| format!("Errore di scrittura del file: {}", e))?;
+                        Ok(())
+                    } else {
+                        Err("Operazione di esportazione annullata.".to_string())
+                    }
+                }, Message::ExportDriftReportResult);
+            } else {
+                state.error_message = Some("Nessun report da esportare.".to_string());
+            }
+        }
+        Message::ExportDriftReportResult(Ok(_)) => {
+            state.success_message = Some("Report esportato con successo!".to_string());
+        }
+        Message::ExportDriftReportResult(Err(e)) => {
+            state.error_message = Some(e);
+        }
+
+        Message::HealthPortsChanged(value) => {
+            state.health_ports = value;
+        }
+        Message::HealthPeriodicToggled(enabled) => {
+            state.health_periodic = enabled;
+            if enabled {
+                return Task::perform(async {}, |_| Message::RunHealthCheck);
+            }
+        }
+        Message::RunHealthCheck => {
+            // Evita controlli sovrapposti quando il tick periodico arriva prima della fine del precedente
+            if state.health_running {
+                return Task::none();
+            }
+            let ports = match parse_ports(&state.health_ports) {
+                Ok(ports) => ports,
+                Err(e) => {
+                    state.error_message = Some(e);
+                    return Task::none();
+                }
+            };
+            state.health_running = true;
+            let lines_to_check = state.file_lines.clone();
+            let ports_setting = state.health_ports.clone();
+
+            return Task::batch(vec![
+                Task::perform(async move {
+                    let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                    db_manager::set_setting(&conn, "health_check_ports", &ports_setting)
+                        .map_err(|e| e.to_string())
+                }, Message::UpdateDatabaseResult),
+                Task::perform(async move {
+                    check_entries(&lines_to_check, &ports)
+                }, Message::HealthCheckResult),
+            ]);
+        }
+        Message::HealthCheckResult(results) => {
+            state.health_running = false;
+            state.health_results.extend(results);
+        }
+        Message::LoadSources => {
+            return Task::perform(async {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                db_manager::get_all_profile_sources(&conn).map_err(|e| e.to_string())
+            }, Message::LoadSourcesResult);
+        }
+        Message::LoadSourcesResult(Ok(sources)) => {
+            // La prima lettura di una sorgente non riscrive il file hosts: una sorgente appena
+            // aggiunta al profilo attivo (anche da un bundle) va quindi scritta qui
+            let active_id = state.selected_profile.as_ref().map(|p| p.id.clone());
+            let added_to_active = state.sources_loaded && sources.iter().any(|source| {
+                Some(&source.profile_id) == active_id.as_ref() && !state.sources.iter().any(|s| s.id == source.id)
+            });
+            state.sources_loaded = true;
+            state.source_cache.retain(|id, _| sources.iter().any(|s| s.id == *id));
+            state.sources = sources;
+            let mut tasks = vec![Task::perform(async {}, |_| Message::RefreshSources)];
+            if added_to_active {
+                tasks.push(write_active_hosts(state));
+            }
+            return Task::batch(tasks);
+        }
+        Message::LoadSourcesResult(Err(e)) => {
+            state.error_message = Some(format!("Error loading sources: {}", e));
+        }
+        Message::RefreshSources => {
+            if state.sources_refreshing || state.sources.is_empty() {
+                return Task::none();
+            }
+            state.sources_refreshing = true;
+            let to_check: Vec<(ProfileSource, Option<_>)> = state.sources
+                .iter()
+                .map(|source| {
+                    // Dopo un errore la sorgente viene riletta finché non torna disponibile
+                    let cached = state.source_cache.get(&source.id).filter(|d| d.error.is_none()).and_then(|d| d.fingerprint);
+                    (source.clone(), cached)
+                })
+                .collect();
+
+            // Rilegge solo i file la cui data di modifica o dimensione è cambiata
+            return Task::perform(async move {
+                to_check
+                    .into_iter()
+                    .filter(|(source, cached)| {
+                        cached.is_none() || fingerprint(Path::new(&source.path)).ok() != *cached
+                    })
+                    .map(|(source, _)| (source.id.clone(), load_source(&source)))
+                    .collect()
+            }, Message::SourcesRefreshed);
+        }
+        Message::SourcesRefreshed(changed) => {
+            state.sources_refreshing = false;
+            if changed.is_empty() {
+                return Task::none();
+            }
+
+            let active_id = state.selected_profile.as_ref().map(|p| p.id.clone());
+            let mut active_changed = false;
+            for (id, data) in changed {
+                // Lo stesso errore a ogni rilettura non viene mostrato di nuovo
+                let previous_error = state.source_cache.get(&id).and_then(|d| d.error.clone());
+                if data.error.is_some() && data.error != previous_error {
+                    state.error_message = data.error.clone();
+                }
+                let is_active = state.sources.iter().any(|s| s.id == id && Some(&s.profile_id) == active_id.as_ref());
+                if update_cache(&mut state.source_cache, id, data) && is_active {
+                    active_changed = true;
+                }
+            }
+
+            if active_changed {
+                return write_active_hosts(state);
+            }
+        }
+        Message::SourceFormatSelected(format) => {
+            state.new_source_format = format;
+        }
+        Message::AddSourceButtonPressed => {
+            let Some(profile_id) = state.editing_profile_id.clone() else {
+                state.error_message = Some("No Profile Selected.".to_string());
+                return Task::none();
+            };
+            let format = state.new_source_format;
+
+            return Task::perform(async move {
+                let file_path = rfd::AsyncFileDialog::new()
+                    .pick_file()
+                    .await;
+
+                if let Some(file) = file_path {
+                    let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                    db_manager::add_profile_source(&conn, &profile_id, &file.path().to_string_lossy(), format)
+                        .map_err(|e| e.to_string())
+                } else {
+                    Err("Operazione annullata.".to_string())
+                }
+            }, Message::SourceChangeResult);
+        }
+        Message::RemoveSource(source_id) => {
+            let active_id = state.selected_profile.as_ref().map(|p| p.id.clone());
+            let was_active = state.sources.iter().any(|s| s.id == source_id && Some(&s.profile_id) == active_id.as_ref());
+            state.source_cache.remove(&source_id);
+            state.sources.retain(|s| s.id != source_id);
+
+            let mut tasks = vec![Task::perform(async move {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                db_manager::delete_profile_source(&conn, &source_id).map_err(|e| e.to_string())
+            }, Message::SourceChangeResult)];
+            if was_active {
+                tasks.push(write_active_hosts(state));
+            }
+            return Task::batch(tasks);
+        }
+        Message::SourceChangeResult(Ok(_)) => {
+            return Task::perform(async {}, |_| Message::LoadSources);
+        }
+        Message::SourceChangeResult(Err(e)) => {
+            state.error_message = Some(e);
+        }
+        Message::LoadHealthPortsResult(Ok(Some(ports))) => {
+            state.health_ports = ports;
+        }
+        Message::LoadHealthPortsResult(Ok(None)) => {
+        }
+        Message::LoadHealthPortsResult(Err(e)) => {
+            state.error_message = Some(format!("Error loading health check settings: {}", e));
+        }
+        Message::StagedEditsLoaded(Ok(value)) => {
+            state.staged_edits = value.as_deref() == Some("1");
+            // Un bundle importato può disattivare la modalità: le modifiche in sospeso vengono applicate
+            if !state.staged_edits && state.pending_changes > 0 {
+                state.pending_changes = 0;
+                return persist_edited_profile(state);
+            }
+        }
+        Message::StagedEditsLoaded(Err(e)) => {
+            state.error_message = Some(format!("Error loading staged edits setting: {}", e));
+        }
+        Message::StagedEditsToggled(enabled) => {
+            state.staged_edits = enabled;
+            let mut tasks = vec![Task::perform(async move {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                db_manager::set_setting(&conn, "staged_edits", if enabled { "1" } else { "0" })
+                    .map_err(|e| e.to_string())
+            }, Message::UpdateDatabaseResult)];
+            // Disattivando la modalità, le modifiche in sospeso vengono applicate
+            if !enabled && state.pending_changes > 0 {
+                state.pending_changes = 0;
+                tasks.push(persist_edited_profile(state));
+            }
+            return Task::batch(tasks);
+        }
+        Message::FlushDnsCacheLoaded(Ok(value)) => {
+            state.flush_dns_cache = value.as_deref() != Some("0");
+        }
+        Message::FlushDnsCacheLoaded(Err(e)) => {
+            state.error_message = Some(format!("Error loading DNS cache setting: {}", e));
+        }
+        Message::FlushDnsCacheToggled(enabled) => {
+            state.flush_dns_cache = enabled;
+            return Task::perform(async move {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                db_manager::set_setting(&conn, "flush_dns_cache", if enabled { "1" } else { "0" })
+                    .map_err(|e| e.to_string())
+            }, Message::UpdateDatabaseResult);
+        }
+        Message::HookSettingsLoaded(Ok(hooks)) => {
+            state.hook_timeout_input = hooks.timeout_secs.to_string();
+            state.hook_draft = hooks.clone();
+            state.hook_settings = hooks;
+        }
+        Message::HookSettingsLoaded(Err(e)) => {
+            state.error_message = Some(format!("Error loading hook settings: {}", e));
+        }
+        Message::PreApplyHookChanged(command) => {
+            state.hook_draft.pre_apply = command;
+        }
+        Message::PostApplyHookChanged(command) => {
+            state.hook_draft.post_apply = command;
+        }
+        Message::HookTimeoutChanged(value) => {
+            state.hook_timeout_input = value;
+        }
+        Message::AbortOnPreHookFailureToggled(abort) => {
+            state.hook_draft.abort_on_pre_failure = abort;
+        }
+        Message::SaveHookSettings => {
+            match state.hook_timeout_input.trim().parse::<u64>() {
+                Ok(secs) if secs > 0 => state.hook_draft.timeout_secs = secs,
+                _ => {
+                    state.error_message = Some(format!("Timeout non valido: '{}'", state.hook_timeout_input));
+                    return Task::none();
+                }
+            }
+            state.hook_settings = state.hook_draft.clone();
+            let hooks = state.hook_settings.clone();
+            state.success_message = Some("Hook salvati.".to_string());
+            return Task::perform(async move {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                db_manager::set_hook_settings(&conn, &hooks).map_err(|e| e.to_string())
+            }, Message::UpdateDatabaseResult);
+        }
+        Message::ClearHookLog => {
+            state.hook_log.clear();
+        }
+        Message::ApplyPendingChanges => {
+            if state.pending_changes > 0 {
+                state.pending_changes = 0;
+                return persist_edited_profile(state);
+            }
+        }
+        Message::DiscardPendingChanges => {
+            // Le modifiche in sospeso non sono mai arrivate al database: basta rileggerlo
+            let Some(profile_id) = state.editing_profile_id.clone() else {
+                return Task::none();
+            };
+            return Task::perform(async move {
+                let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                db_manager::get_profile_hosts(&conn, &profile_id).map_err(|e| e.to_string())
+            }, Message::PendingChangesDiscarded);
+        }
+        Message::PendingChangesDiscarded(Ok(lines)) => {
+            state.issues_stale = true;
+            state.file_lines = Arc::new(lines);
+            state.pending_changes = 0;
+            state.editing_index = None;
+            state.highlighted_index = None;
+            state.transferring_index = None;
+            clear_selection(state);
+            state.success_message = Some("Modifiche annullate.".to_string());
+        }
+        Message::PendingChangesDiscarded(Err(e)) => {
+            state.error_message = Some(format!("Errore nel ripristino del profilo: {}", e));
+        }
+
+    }
+
+    Task::none()
+}
+// Salva l'ultimo server DNS usato, così viene riproposto al prossimo avvio
+fn remember_dns_server(dns_server: &str) -> Task<Message> {
+    let dns_server = dns_server.to_string();
+    Task::perform(async move {
+        let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+        db_manager::set_setting(&conn, "last_dns_server", &dns_server)
+            .map_err(|e| e.to_string())
+    }, Message::UpdateDatabaseResult)
+}
+
+pub fn view(state: &MyApp) -> Element<'_, Message> {
+    match state.view {
         View::Main => main_view(state),
         View::Profiles => profile_view::view(state),
+        View::Drift => drift_view::view(state),
     }
 }
 
 
-fn main_view(state: &MyApp) -> Element<Message> {
+fn main_view(state: &MyApp) -> Element<'_, Message> {
 
 
     let status_label: Text<'_, Theme, Renderer> = if let Some(msg) = &state.error_message {
@@ -541,11 +1859,29 @@ fn main_view(state: &MyApp) -> Element<Message> {
         .map(|p| p.name.clone())
         .unwrap_or_else(|| "No Profile Selected".to_string());
 
-    let profile_info_row = row![
-        text(format!("Profilo attuale: {}", selected_profile_name))
+    // Se il profilo in modifica non è quello attivo, le modifiche non toccano il file hosts
+    let profile_label: Element<Message> = match state.editing_profile() {
+        Some(editing) if !state.is_editing_active() => row![
+            text(format!("In modifica: {}  -  attivo: {}", editing.name, selected_profile_name))
+                .size(18)
+                .color(Color::from_rgb(0.9, 0.6, 0.1)),
+            button("Attiva").on_press(Message::ProfileSelected(editing.id.clone())),
+        ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+            .into(),
+        _ => text(format!("Profilo attuale: {}", selected_profile_name))
             .size(18)
-            .color(Color::from_rgb(0.9, 0.9, 0.9)),
+            .color(Color::from_rgb(0.9, 0.9, 0.9))
+            .into(),
+    };
+
+    let profile_info_row = row![
+        profile_label,
         Space::with_width(Length::Fill),
+        button("Confronta con DNS")
+            .on_press(Message::DriftReportButtonPressed)
+            .width(Length::Shrink),
         button("Gestisci Profili")
             .on_press(Message::ShowProfilesView)
             .width(Length::Shrink),
@@ -553,6 +1889,72 @@ fn main_view(state: &MyApp) -> Element<Message> {
         .spacing(10)
         .align_y(Alignment::Center);
 
+    let pending_bar: Element<Message> = if state.pending_changes > 0 {
+        container(
+            row![
+                text(format!("{} modifiche non applicate", state.pending_changes))
+                    .size(16)
+                    .color(Color::from_rgb(0.9, 0.6, 0.1))
+                    .width(Length::Fill),
+                button("Applica").on_press(Message::ApplyPendingChanges),
+                button("Annulla modifiche").on_press(Message::DiscardPendingChanges),
+            ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+        )
+            .padding(10)
+            .style(container::rounded_box)
+            .into()
+    } else {
+        Space::with_height(0).into()
+    };
+
+    // Il profilo non applicato conta solo finché è ancora quello attivo
+    let not_applied = state.hosts_not_applied.is_some()
+        && state.hosts_not_applied.as_ref() == state.selected_profile.as_ref().map(|p| &p.id);
+    let not_applied_bar: Element<Message> = if not_applied {
+        container(
+            row![
+                text(format!("Profilo {} non applicato al file hosts", selected_profile_name))
+                    .size(16)
+                    .color(Color::from_rgb(0.8, 0.0, 0.0))
+                    .width(Length::Fill),
+                button("Riprova").on_press(Message::RetryApply),
+            ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+        )
+            .padding(10)
+            .style(container::rounded_box)
+            .into()
+    } else {
+        Space::with_height(0).into()
+    };
+
+    let health_summary = match summarize(&state.file_lines, &state.health_results) {
+        Some((reachable, checked)) => format!("Raggiungibili: {}/{}", reachable, checked),
+        None => String::new(),
+    };
+
+    let health_check_row = row![
+        text("Porte:").size(14),
+        text_input("80,443", &state.health_ports)
+            .on_input(Message::HealthPortsChanged)
+            .width(Length::Fixed(120.0)),
+        button(if state.health_running { "Controllo in corso..." } else { "Controlla raggiungibilità" })
+            .on_press(Message::RunHealthCheck),
+        checkbox(format!("Ogni {}s", HEALTH_CHECK_INTERVAL.as_secs()), state.health_periodic)
+            .on_toggle(Message::HealthPeriodicToggled),
+        Space::with_width(Length::Fill),
+        checkbox("Applica le modifiche manualmente", state.staged_edits)
+            .on_toggle(Message::StagedEditsToggled),
+        checkbox("Svuota cache DNS", state.flush_dns_cache)
+            .on_toggle(Message::FlushDnsCacheToggled),
+        text(health_summary).size(14),
+    ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
     let dns_lookup_section = row![
         text_input(
             "Hostname per DNS Lookup (es: google.com)",
@@ -566,6 +1968,10 @@ fn main_view(state: &MyApp) -> Element<Message> {
         )
         .on_input(Message::InputDNSChanged)
         .width(Length::Fill),
+        pick_list(dns_server_options(&state.dns_settings), None::<String>, Message::DnsServerSelected)
+            .placeholder("Server noti"),
+        button(if state.dns_settings.favourite_servers.contains(&state.input_text_dns) { "- Preferito" } else { "+ Preferito" })
+            .on_press(Message::ToggleFavouriteDnsServer),
         button("Cerca IP (DNS)").on_press(Message::DnsLookupButtonPressed),
     ]
         .spacing(10)
@@ -578,6 +1984,7 @@ fn main_view(state: &MyApp) -> Element<Message> {
         )
         .on_input(Message::InputIpChanged)
         .width(Length::Fill),
+        button("PTR").on_press(Message::ReverseLookupButtonPressed),
         text_input(
             "Hostname (es: server.local)",
             &state.input_hostname,
@@ -589,12 +1996,25 @@ fn main_view(state: &MyApp) -> Element<Message> {
         .spacing(10)
         .align_y(Alignment::Center);
 
+    let reverse_candidates_row = row(
+        state.reverse_candidates
+            .iter()
+            .map(|hostname| {
+                button(text(hostname).size(14))
+                    .on_press(Message::ReverseCandidateSelected(hostname.clone()))
+                    .into()
+            })
+    )
+        .spacing(5)
+        .align_y(Alignment::Center);
+
     let add_host_section = container(
         column![
             text("Aggiungi un nuovo record:").size(22).color(Color::from_rgb(0.1, 0.5, 0.8)),
             dns_lookup_section,
             text("oppure").size(14).color(Color::from_rgb(0.5, 0.5, 0.5)),
             manual_add_section,
+            reverse_candidates_row,
             status_label,
         ]
             .spacing(10)
@@ -602,9 +2022,68 @@ fn main_view(state: &MyApp) -> Element<Message> {
         .padding(15)
         .style(container::rounded_box);
 
-    let entries: Vec<Element<Message>> = state.file_lines
+    let lookup_details_section: Element<Message> = match &state.lookup_details {
+        Some(details) => {
+            let chain = details.cname_chain
+                .iter()
+                .map(|name| {
+                    row![
+                        text(name).width(Length::Fill),
+                        button(text("Usa").size(14)).on_press(Message::UseLookupName(name.clone())),
+                    ]
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .into()
+                });
+            let records = details.records
+                .iter()
+                .map(|record| {
+                    text(format!("{:<30} {:>6}s  {:<6} {}", record.name, record.ttl, record.record_type, record.data))
+                        .size(14)
+                        .color(Color::from_rgb(0.6, 0.6, 0.6))
+                        .into()
+                });
+
+            container(
+                column![
+                    row![
+                        text(format!("Dettagli lookup: {}", details.hostname)).size(18).width(Length::Fill),
+                        button(text("Chiudi").size(14)).on_press(Message::CloseLookupDetails),
+                    ]
+                        .align_y(Alignment::Center),
+                    text(format!("Server interrogato: {}  -  Tempo di risposta: {} ms", details.server, details.elapsed_ms)).size(14),
+                    text("Catena CNAME:").size(14),
+                    column(chain).spacing(5),
+                    text("Record:").size(14),
+                    column(records).spacing(2),
+                ]
+                    .spacing(8)
+            )
+                .padding(15)
+                .style(container::rounded_box)
+                .into()
+        }
+        None => Space::with_height(0).into(),
+    };
+
+    // Solo la pagina corrente viene trasformata in widget: con blocklist da decine di migliaia
+    // di righe costruire un row! per ogni riga rende l'interfaccia inutilizzabile.
+    let profile_choices: Vec<ProfileChoice> = state.profiles
+        .iter()
+        .filter(|p| state.editing_profile_id.as_ref() != Some(&p.id))
+        .map(|p| ProfileChoice { id: p.id.clone(), name: p.name.clone() })
+        .collect();
+
+    let visible = state.entry_filter.visible_indices(&state.file_lines, &state.collapsed_sections);
+    let issues = &state.issues;
+    let pages = page_count(visible.len());
+    let current_page = state.entries_page.min(pages - 1);
+
+    let entries: Vec<Element<Message>> = visible
         .iter()
-        .enumerate()
+        .skip(current_page * ENTRIES_PAGE_SIZE)
+        .take(ENTRIES_PAGE_SIZE)
+        .map(|&index| (index, &state.file_lines[index]))
         .map(|(index, line)| {
             match line {
                 Line::Entry(entry) => {
@@ -623,20 +2102,66 @@ fn main_view(state: &MyApp) -> Element<Message> {
                             .padding(5)
                             .align_y(Alignment::Center)
                             .into()
+                    } else if state.transferring_index == Some(index) {
+                        row![
+                            text(format!("{:<15} {}", entry.ip, entry.hostname)).width(Length::Fill),
+                            pick_list(profile_choices.clone(), state.transfer_target.clone(), Message::TransferTargetSelected)
+                                .placeholder("Profilo di destinazione"),
+                            button("Copia").on_press(Message::CopyEntryToProfile),
+                            button("Sposta").on_press(Message::MoveEntryToProfile),
+                            button("Annulla").on_press(Message::CancelTransfer),
+                        ]
+                            .spacing(5)
+                            .padding(5)
+                            .align_y(Alignment::Center)
+                            .into()
                     } else {
-                        let is_localhost = entry.ip == "127.0.0.1" && entry.hostname == "localhost";
-                        let mut delete_button  =  button("Elimina").on_press(Message::DeleteEntry(index)).into();
+                        let is_localhost = is_protected_localhost(entry);
+                        let move_up_button = button("Su").on_press(Message::MoveLineUp(index));
+                        let move_down_button = button("Giù").on_press(Message::MoveLineDown(index));
+                        let mut delete_button  =  button("Elimina").on_press(Message::DeleteEntry(index));
                         let mut modify_button =  button("Modifica").on_press(Message::EditEntry(index));
+                        let transfer_button = button("Copia/Sposta").on_press(Message::TransferEntry(index));
 
                         if is_localhost {
                             delete_button= button("Elimina");
                             modify_button= button("Modifica");
                         }
                         
+                        let health_badge = match state.health_results.get(&entry.ip) {
+                            Some(result) if result.status == HealthStatus::Reachable => text(format!(
+                                "OK {}ms :{}", result.latency_ms.unwrap_or(0), result.port.unwrap_or(0)))
+                                .size(14)
+                                .color(Color::from_rgb(0.2, 0.7, 0.2)),
+                            Some(_) => text("KO").size(14).color(Color::from_rgb(0.8, 0.2, 0.2)),
+                            None => text(""),
+                        };
+
+                        let mut entry_text = text(format!("{:<15} {}", entry.ip, entry.hostname))
+                            .width(Length::Fill);
+                        if state.highlighted_index == Some(index) {
+                            entry_text = entry_text.color(Color::from_rgb(0.9, 0.7, 0.1));
+                        }
+
+                        let issue_label = match issues.get(&index) {
+                            Some(issue) => text(issue.message()).size(14).color(Color::from_rgb(0.9, 0.6, 0.1)),
+                            None => text(""),
+                        };
+
+                        let mut select_box = checkbox("", state.selected_lines.contains(&index));
+                        if !is_localhost {
+                            select_box = select_box.on_toggle(move |checked| Message::LineSelectionToggled(index, checked));
+                        }
+
                         row![
-                            text(format!("{:<15} {}", entry.ip, entry.hostname))
-                                .width(Length::Fill),
+                            select_box,
+                            entry_text,
+                            issue_label,
+                            health_badge,
+                            move_up_button,
+                            move_down_button,
                             modify_button,
+                            transfer_button,
                             delete_button,
                         ]
                             .spacing(5)
@@ -647,19 +2172,186 @@ fn main_view(state: &MyApp) -> Element<Message> {
                 },
                 Line::Comment(comment) => text(comment).color(Color::from_rgb(0.5, 0.5, 0.5)).into(),
                 Line::Empty => text("").into(),
+                Line::Section(name) => {
+                    let collapsed = state.collapsed_sections.contains(name);
+                    row![
+                        text(name).size(18).color(Color::from_rgb(0.1, 0.5, 0.8)).width(Length::Fill),
+                        button(if collapsed { "Espandi" } else { "Comprimi" })
+                            .on_press(Message::ToggleSectionCollapsed(name.clone())),
+                        button("Su").on_press(Message::MoveLineUp(index)),
+                        button("Giù").on_press(Message::MoveLineDown(index)),
+                        button("Elimina").on_press(Message::DeleteEntry(index)),
+                    ]
+                        .spacing(5)
+                        .padding(5)
+                        .align_y(Alignment::Center)
+                        .into()
+                }
             }
         })
         .collect();
 
+    let active_sources: Vec<Element<Message>> = state.sources
+        .iter()
+        .filter(|source| state.editing_profile_id.as_ref() == Some(&source.profile_id))
+        .map(|source| {
+            let status = match state.source_cache.get(&source.id) {
+                Some(data) if data.error.is_some() && data.lines.is_empty() => "errore di lettura".to_string(),
+                Some(data) if data.error.is_some() => format!("errore di lettura, {} record dall'ultima lettura", data.lines.len()),
+                Some(data) if data.skipped > 0 => format!("{} record, {} righe non valide ignorate", data.lines.len(), data.skipped),
+                Some(data) => format!("{} record", data.lines.len()),
+                None => "in caricamento...".to_string(),
+            };
+            row![
+                text(format!("{} ({})", source.path, source.format)).size(14).width(Length::Fill),
+                text(status).size(14).color(Color::from_rgb(0.5, 0.5, 0.5)),
+                button(text("Rimuovi").size(14)).on_press(Message::RemoveSource(source.id.clone())),
+            ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+                .into()
+        })
+        .collect();
+
+    let sources_section = column![
+        row![
+            text("Sorgenti (sola lettura):").size(16).width(Length::Fill),
+            pick_list(SourceFormat::ALL, Some(state.new_source_format), Message::SourceFormatSelected),
+            button("Aggiungi sorgente").on_press(Message::AddSourceButtonPressed),
+        ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        column(active_sources).spacing(5),
+    ]
+        .spacing(5);
+
+    let mut previous_page = button("< Precedente");
+    if current_page > 0 {
+        previous_page = previous_page.on_press(Message::EntriesPageChanged(current_page - 1));
+    }
+    let mut next_page = button("Successiva >");
+    if current_page + 1 < pages {
+        next_page = next_page.on_press(Message::EntriesPageChanged(current_page + 1));
+    }
+    let lines_summary = if state.entry_filter.is_active() {
+        format!("{} di {} righe", visible.len(), state.file_lines.len())
+    } else {
+        format!("{} righe", state.file_lines.len())
+    };
+    let pager_row = row![
+        previous_page,
+        text(format!("Pagina {} di {} ({})", current_page + 1, pages, lines_summary)).size(14),
+        next_page,
+    ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    let bulk_row: Element<Message> = if state.selected_lines.is_empty() {
+        row![
+            Space::with_width(Length::Fill),
+            button("Seleziona visibili").on_press(Message::SelectVisibleLines),
+        ]
+            .into()
+    } else {
+        column![
+            row![
+                text(format!("{} selezionati", state.selected_lines.len())).size(14).width(Length::Fill),
+                button("Abilita").on_press(Message::BulkSetEnabled(true)),
+                button("Disabilita").on_press(Message::BulkSetEnabled(false)),
+                button("Elimina").on_press(Message::BulkDelete),
+                button("Seleziona visibili").on_press(Message::SelectVisibleLines),
+                button("Deseleziona").on_press(Message::ClearSelection),
+            ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            row![
+                text_input("Nuovo IP per i selezionati", &state.bulk_ip)
+                    .on_input(Message::BulkIpChanged)
+                    .on_submit(Message::BulkChangeIp)
+                    .width(Length::Fill),
+                button("Cambia IP").on_press(Message::BulkChangeIp),
+                pick_list(profile_choices.clone(), state.transfer_target.clone(), Message::TransferTargetSelected)
+                    .placeholder("Profilo di destinazione"),
+                button("Copia").on_press(Message::BulkCopyToProfile),
+                button("Sposta").on_press(Message::BulkMoveToProfile),
+            ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+        ]
+            .spacing(5)
+            .into()
+    };
+
+    let section_row = row![
+        text_input("Nuova sezione (es: Ambiente di sviluppo)", &state.new_section_name)
+            .on_input(Message::NewSectionNameChanged)
+            .on_submit(Message::AddSectionButtonPressed)
+            .width(Length::Fill),
+        button("Aggiungi sezione").on_press(Message::AddSectionButtonPressed),
+    ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    let filter_row = row![
+        text_input("Cerca hostname, IP o commento", &state.entry_filter.query)
+            .on_input(Message::FilterQueryChanged)
+            .width(Length::Fill),
+        checkbox("Regex", state.entry_filter.use_regex)
+            .on_toggle(Message::FilterRegexToggled),
+        pick_list(StatusFilter::ALL, Some(state.entry_filter.status), Message::FilterStatusSelected),
+        pick_list(IpVersionFilter::ALL, Some(state.entry_filter.ip_version), Message::FilterIpVersionSelected),
+        button("Azzera filtri").on_press(Message::ClearFilter),
+    ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    let issues_row: Element<Message> = if issues.is_empty() {
+        Space::with_height(0).into()
+    } else {
+        let count = |kind: IssueKind| issues.values().filter(|issue| issue.kind == kind).count();
+        row![
+            text(format!(
+                "Avvisi: {} duplicati, {} conflitti, {} righe ignorate, {} su localhost",
+                count(IssueKind::Duplicate),
+                count(IssueKind::Conflict),
+                count(IssueKind::Shadowed),
+                count(IssueKind::LocalhostCollision),
+            ))
+                .size(14)
+                .color(Color::from_rgb(0.9, 0.6, 0.1))
+                .width(Length::Fill),
+            button("Correggi duplicati").on_press(Message::FixDuplicates),
+        ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+            .into()
+    };
+
+    let filter_error: Element<Message> = match &state.entry_filter.error {
+        Some(error) => text(error).size(14).color(Color::from_rgb(0.8, 0.2, 0.2)).into(),
+        None => Space::with_height(0).into(),
+    };
+
     let scrollable_entries = scrollable(column(entries).padding(5).spacing(5)).height(Length::Fill).spacing(10);
 
     let content = column![
         Space::with_height(10),
         profile_info_row,
+        pending_bar,
+        not_applied_bar,
+        health_check_row,
         Space::with_height(20),
         add_host_section,
+        lookup_details_section,
         Space::with_height(20),
         text("Record nel file hosts:").size(22).color(Color::from_rgb(0.1, 0.5, 0.8)),
+        sources_section,
+        issues_row,
+        section_row,
+        filter_row,
+        filter_error,
+        bulk_row,
+        pager_row,
         scrollable_entries,
     ]
         .spacing(15)
@@ -668,38 +2360,402 @@ fn main_view(state: &MyApp) -> Element<Message> {
     content.into()
 }
 
+pub const ENTRIES_PAGE_SIZE: usize = 200;
+const HOOK_LOG_LIMIT: usize = 50;
+
+// Righe che si possono selezionare per le azioni di gruppo: il record localhost
+// non si modifica né si elimina, nemmeno così
+fn is_selectable(line: &Line) -> bool {
+    matches!(line, Line::Entry(entry) if !is_protected_localhost(entry))
+}
+
+// Gli indici selezionati non sono più validi quando le righe cambiano posizione
+fn clear_selection(state: &mut MyApp) {
+    state.selected_lines.clear();
+    state.last_selected_line = None;
+}
+
+fn remove_lines(state: &mut MyApp, indices: &BTreeSet<usize>) {
+    let mut index = 0;
+    state.issues_stale = true;
+    Arc::make_mut(&mut state.file_lines).retain(|_| {
+        let keep = !indices.contains(&index);
+        index += 1;
+        keep
+    });
+    state.editing_index = None;
+    state.highlighted_index = None;
+    state.transferring_index = None;
+    clear_selection(state);
+}
+
+// Copia o sposta le righe indicate del profilo attivo in un altro profilo. Lo spostamento
+// viene applicato subito in memoria; il database aggiorna entrambi i profili in un'unica
+// transazione e il file hosts viene riscritto solo a transazione riuscita.
+fn transfer_lines(state: &mut MyApp, indices: BTreeSet<usize>, move_lines: bool) -> Task<Message> {
+    let Some(target) = state.transfer_target.clone() else {
+        state.error_message = Some("Seleziona il profilo di destinazione.".to_string());
+        return Task::none();
+    };
+    let lines: Vec<Line> = indices
+        .iter()
+        .filter_map(|&index| match state.file_lines.get(index) {
+            Some(line @ Line::Entry(_)) => Some(line.clone()),
+            _ => None,
+        })
+        .collect();
+    if lines.is_empty() {
+        return Task::none();
+    }
+
+    let from_active = state.is_editing_active();
+    if move_lines && state.pending_changes > 0 {
+        state.error_message = Some("Applica o annulla le modifiche in sospeso prima di spostare record.".to_string());
+        return Task::none();
+    }
+    // Il file hosts va riscritto solo se il profilo attivo è l'origine o la destinazione
+    let active_id = state.selected_profile.as_ref().map(|p| p.id.clone());
+    let target_is_active = active_id.as_ref() == Some(&target.id);
+    let mut remaining_source = None;
+    if move_lines {
+        let Some(source_id) = state.editing_profile_id.clone() else {
+            state.error_message = Some("No Profile Selected.".to_string());
+            return Task::none();
+        };
+        remove_lines(state, &indices);
+        remaining_source = Some((source_id, state.file_lines.clone()));
+    }
+    state.transferring_index = None;
+
+    let moved_from_active = move_lines && from_active;
+    let sources = match &active_id {
+        Some(id) if target_is_active || moved_from_active => Some(active_source_lines(&state.sources, &state.source_cache, id)),
+        _ => None,
+    };
+    let options = apply_options(state);
+    let count = lines.len();
+    Task::perform(async move {
+        let database_error = |e: rusqlite::Error| ApplyError { message: e.to_string(), permission_denied: false, hook_runs: Vec::new() };
+        let mut target_hosts = Vec::new();
+        let transfer = |target_hosts: &mut Vec<Line>| -> Result<(), String> {
+            let mut conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+            let source = remaining_source.as_ref().map(|(id, hosts)| (id.as_str(), hosts.as_slice()));
+            *target_hosts = db_manager::transfer_entries(&mut conn, &target.id, &lines, source).map_err(|e| e.to_string())?;
+            Ok(())
+        };
+
+        // Se il profilo attivo cambia, il database viene aggiornato solo dopo la scrittura del file hosts
+        let report = match sources {
+            Some(sources) => {
+                let active_hosts = if target_is_active {
+                    let conn = db_manager::initialize_db().map_err(database_error)?;
+                    let mut hosts = db_manager::get_profile_hosts(&conn, &target.id).map_err(database_error)?;
+                    hosts.extend_from_slice(&lines);
+                    hosts
+                } else {
+                    remaining_source.as_ref().map(|(_, hosts)| hosts.to_vec()).unwrap_or_default()
+                };
+                Some(apply_and_commit(&lines_with_sources(&active_hosts, &resolve_sources(&sources)), &options, || transfer(&mut target_hosts))?)
+            }
+            None => {
+                transfer(&mut target_hosts).map_err(|message| ApplyError { message, permission_denied: false, hook_runs: Vec::new() })?;
+                None
+            }
+        };
+        let verb = if move_lines { "spostati" } else { "copiati" };
+        Ok(TransferOutcome {
+            target_id: target.id,
+            target_hosts,
+            report,
+            message: format!("{} record {} in '{}'.", count, verb, target.name),
+        })
+    }, Message::EntriesTransferred)
+}
+
+// Scambia due righe adiacenti (anche intestazioni di sezione), spostando con loro
+// la riga in modifica o evidenziata, e salva il profilo
+fn swap_lines(state: &mut MyApp, first: usize, second: usize) -> Task<Message> {
+    state.issues_stale = true;
+    Arc::make_mut(&mut state.file_lines).swap(first, second);
+    let follow = |index: Option<usize>| match index {
+        Some(i) if i == first => Some(second),
+        Some(i) if i == second => Some(first),
+        other => other,
+    };
+    state.editing_index = follow(state.editing_index);
+    state.highlighted_index = follow(state.highlighted_index);
+    state.transferring_index = follow(state.transferring_index);
+    clear_selection(state);
+    if state.editing_profile_id.is_some() {
+        return save_edited_profile(state);
+    }
+    Task::none()
+}
+
+// Numero di pagine della lista record, almeno una anche con profilo vuoto
+pub fn page_count(lines: usize) -> usize {
+    lines.div_ceil(ENTRIES_PAGE_SIZE).max(1)
+}
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const SOURCES_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+fn subscription(state: &MyApp) -> Subscription<Message> {
+    let mut subscriptions = Vec::new();
+    if state.health_periodic {
+        subscriptions.push(Subscription::run_with_id("health-check", ticks(HEALTH_CHECK_INTERVAL, Message::RunHealthCheck)));
+    }
+    if !state.sources.is_empty() {
+        subscriptions.push(Subscription::run_with_id("sources-refresh", ticks(SOURCES_REFRESH_INTERVAL, Message::RefreshSources)));
+    }
+    // Serve a riconoscere shift+clic per la selezione a intervalli
+    subscriptions.push(iced::event::listen_with(|event, _status, _window| match event {
+        iced::Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => Some(Message::ModifiersChanged(modifiers)),
+        _ => None,
+    }));
+    Subscription::batch(subscriptions)
+}
+
+// Il timer gira su un thread dedicato: l'executor di iced non offre un timer asincrono
+// senza abilitare tokio, che andrebbe in conflitto con il runtime del resolver sincrono.
+// Il thread parte solo quando la subscription viene avviata e termina quando viene rimossa.
+fn ticks(interval: Duration, message: Message) -> impl Stream<Item = Message> {
+    iced::stream::channel(1, move |mut output| async move {
+        let (mut tick_sender, mut tick_receiver) = iced::futures::channel::mpsc::channel::<()>(1);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            if tick_sender.is_closed() {
+                break;
+            }
+            let _ = tick_sender.try_send(());
+        });
+
+        while tick_receiver.next().await.is_some() {
+            if output.send(message.clone()).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+// Da chiamare dopo ogni modifica al profilo aperto. In modalità differita la modifica
+// viene solo contata e resta in memoria fino ad "Applica".
+fn save_edited_profile(state: &mut MyApp) -> Task<Message> {
+    if state.staged_edits {
+        state.pending_changes += 1;
+        return Task::none();
+    }
+    persist_edited_profile(state)
+}
+
+// Salva il profilo in modifica nel database. Se è anche quello attivo, salvataggio e
+// scrittura del file hosts avvengono insieme in `apply_active_profile`.
+fn persist_edited_profile(state: &MyApp) -> Task<Message> {
+    let Some(profile_id) = state.editing_profile_id.clone() else {
+        return Task::none();
+    };
+    if state.is_editing_active() {
+        return apply_active_profile(state, true, false);
+    }
+    let lines = state.file_lines.clone();
+    Task::perform(async move {
+        let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+        db_manager::update_profile_hosts(&conn, &profile_id, &lines)
+            .map_err(|e| e.to_string())
+    }, Message::UpdateDatabaseResult)
+}
+
+// Applica il profilo attivo: le sue righe (se `save_hosts`) e l'attivazione (se `activate`)
+// vengono confermate nel database solo se la scrittura del file hosts riesce.
+// L'esito arriva come un unico `HostsApplied`.
+fn apply_active_profile(state: &MyApp, save_hosts: bool, activate: bool) -> Task<Message> {
+    let Some(profile) = &state.selected_profile else {
+        return Task::none();
+    };
+    // Con modifiche in sospeso sul profilo attivo il file hosts si costruisce dalle righe
+    // salvate nel database: solo "Applica" scrive quelle ancora in memoria
+    let staged = state.pending_changes > 0 && state.is_editing_active();
+    let hosts = if staged {
+        None
+    } else {
+        match state.active_lines() {
+            Some(lines) => Some(lines),
+            None => return Task::none(),
+        }
+    };
+    let sources = active_source_lines(&state.sources, &state.source_cache, &profile.id);
+    let options = apply_options(state);
+    let update = ProfileUpdate {
+        profile_id: profile.id.clone(),
+        hosts,
+        save_hosts: save_hosts && !staged,
+        activate,
+    };
+    Task::perform(async move {
+        apply_profile(&update, &resolve_sources(&sources), &options)
+    }, Message::HostsApplied)
+}
+
+// Cosa fare quando il file hosts non è scrivibile, a seconda della piattaforma
+fn permission_guidance() -> &'static str {
+    if cfg!(windows) {
+        "Avvia l'applicazione come amministratore e premi \"Riprova\"."
+    } else if cfg!(target_os = "macos") {
+        "Premi \"Riprova\" e inserisci la password di amministratore nella finestra di sistema."
+    } else {
+        "Premi \"Riprova\" e autenticati nella finestra di sistema; senza pkexec esegui `sudo -v` in un terminale prima di riprovare."
+    }
+}
+
+// Aggiorna il profilo non applicato e lo salva nel database solo se cambia
+fn set_hosts_not_applied(state: &mut MyApp, profile_id: Option<String>) -> Task<Message> {
+    if state.hosts_not_applied == profile_id {
+        return Task::none();
+    }
+    state.hosts_not_applied = profile_id.clone();
+    Task::perform(async move {
+        let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+        let result = match profile_id {
+            Some(id) => db_manager::set_setting(&conn, db_manager::NOT_APPLIED_KEY, &id),
+            None => db_manager::delete_setting(&conn, db_manager::NOT_APPLIED_KEY),
+        };
+        result.map_err(|e| e.to_string())
+    }, Message::UpdateDatabaseResult)
+}
+
+// Messaggio d'errore di un'applicazione, con le istruzioni se mancano i permessi
+fn apply_error_message(e: &ApplyError) -> String {
+    if e.permission_denied {
+        format!("{}. {}", e.message, permission_guidance())
+    } else {
+        e.message.clone()
+    }
+}
+
+// Aggiunge gli hook eseguiti al log, tenendo solo gli ultimi HOOK_LOG_LIMIT
+fn record_hook_runs(state: &mut MyApp, runs: Vec<HookRun>) {
+    state.hook_log.extend(runs);
+    let overflow = state.hook_log.len().saturating_sub(HOOK_LOG_LIMIT);
+    state.hook_log.drain(..overflow);
+}
+
+// Riscrive il file hosts con il profilo attivo, qualunque sia quello in modifica
+fn write_active_hosts(state: &MyApp) -> Task<Message> {
+    apply_active_profile(state, false, false)
+}
+
+// Apre un profilo nella vista principale senza attivarlo. Le righe del profilo lasciato
+// vengono riportate nella sua copia in `profiles`, che torna così aggiornata.
+fn open_profile(state: &mut MyApp, profile_id: &str) -> Result<(), String> {
+    if state.editing_profile_id.as_deref() == Some(profile_id) {
+        return Ok(());
+    }
+    if state.pending_changes > 0 {
+        return Err(format!("Ci sono {} modifiche non applicate: applicale o annullale prima di cambiare profilo.", state.pending_changes));
+    }
+    let Some(hosts) = state.profiles.iter().find(|p| p.id == profile_id).map(|p| p.hosts.clone()) else {
+        return Err("Profilo non trovato.".to_string());
+    };
+    if let Some(previous_id) = state.editing_profile_id.take()
+        && let Some(previous) = state.profiles.iter_mut().find(|p| p.id == previous_id) {
+        previous.hosts = state.file_lines.to_vec();
+    }
+
+    state.issues_stale = true;
+    state.file_lines = Arc::new(hosts);
+    state.editing_profile_id = Some(profile_id.to_string());
+    state.entries_page = 0;
+    state.editing_index = None;
+    state.highlighted_index = None;
+    state.transferring_index = None;
+    clear_selection(state);
+    Ok(())
+}
+
+fn apply_options(state: &MyApp) -> ApplyOptions {
+    ApplyOptions {
+        profile_name: state.selected_profile.as_ref().map(|p| p.name.clone()).unwrap_or_default(),
+        flush_dns: state.flush_dns_cache,
+        hooks: state.hook_settings.clone(),
+    }
+}
+
+// Sorgenti del profilo con le righe in cache; quelle mancanti vengono lette da `resolve_sources`
+fn active_source_lines(
+    sources: &[ProfileSource],
+    cache: &HashMap<String, SourceData>,
+    profile_id: &str,
+) -> Vec<PendingSource> {
+    sources
+        .iter()
+        .filter(|source| source.profile_id == profile_id)
+        .map(|source| (source.clone(), cache.get(&source.id).map(|data| data.lines.clone())))
+        .collect()
+}
+
+// Opzioni del menu DNS: preferiti, resolver di sistema e server pubblici noti, senza duplicati
+fn dns_server_options(settings: &DnsSettings) -> Vec<String> {
+    let mut options: Vec<String> = Vec::new();
+    let candidates = settings.favourite_servers.iter().cloned()
+        .chain(settings.system_servers.iter().cloned())
+        .chain(KNOWN_DNS_SERVERS.iter().map(|s| s.to_string()));
+    for server in candidates {
+        if !options.contains(&server) {
+            options.push(server);
+        }
+    }
+    options
+}
+
 fn theme(_state: &MyApp) -> Theme {
     Theme::TokyoNight
 }
 
 
 pub fn init_app() -> iced::Result {
-    let initial_state = MyApp {
-        input_text: String::new(),
-        input_text_dns: "10.10.10.10".to_string(),
-        input_ip: String::new(),
-        input_hostname: String::new(),
-        file_lines: Vec::new(),
-        editing_index: None,
-        editing_ip: String::new(),
-        editing_hostname: String::new(),
-        error_message: None,
-        success_message: None,
-        profiles: Vec::new(),
-        selected_profile: None,
-        new_profile_name: String::new(),
-        view: View::Main,
-    };
+    let initial_state = MyApp::default();
 
 
     iced::application("Hosts manager", update, view)
         .theme(theme)
+        .subscription(subscription)
         .settings(Settings {
             ..Default::default()
         })
         .run_with(|| {
-            (initial_state, Task::perform(async {}, |_| Message::LoadProfiles))
+            (initial_state, Task::batch(vec![
+                Task::perform(async {}, |_| Message::LoadProfiles),
+                Task::perform(async {}, |_| Message::LoadSources),
+                load_settings(),
+                Task::perform(async {
+                    let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+                    db_manager::get_setting(&conn, db_manager::NOT_APPLIED_KEY).map_err(|e| e.to_string())
+                }, Message::HostsNotAppliedLoaded),
+            ]))
         })
 
 }
 
+// Legge tutte le impostazioni salvate: all'avvio e dopo l'importazione di un bundle
+fn load_settings() -> Task<Message> {
+    Task::batch(vec![
+        Task::perform(async {}, |_| Message::LoadDnsSettings),
+        Task::perform(async {
+            let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+            db_manager::get_setting(&conn, "health_check_ports").map_err(|e| e.to_string())
+        }, Message::LoadHealthPortsResult),
+        Task::perform(async {
+            let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+            db_manager::get_setting(&conn, "staged_edits").map_err(|e| e.to_string())
+        }, Message::StagedEditsLoaded),
+        Task::perform(async {
+            let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+            db_manager::get_setting(&conn, "flush_dns_cache").map_err(|e| e.to_string())
+        }, Message::FlushDnsCacheLoaded),
+        Task::perform(async {
+            let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+            db_manager::get_hook_settings(&conn).map_err(|e| e.to_string())
+        }, Message::HookSettingsLoaded),
+    ])
+}
+
@@ -0,0 +1,171 @@
+use std::borrow::Borrow;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db_manager;
+
+use crate::helper::{restore_hosts_content, validate_lines, write_hosts_lines, WriteError};
+use crate::dns_cache::summarize_flush;
+use crate::hooks::{hosts_diff, run_hook, HookContext, HookRun, HookSettings, HookStage};
+use crate::sources::lines_with_sources;
+use crate::host_manager::{get_hosts_file_path, render_hosts_file, Line};
+
+// Cosa fare attorno alla scrittura del file hosts
+#[derive(Debug, Clone)]
+pub struct ApplyOptions {
+    pub profile_name: String,
+    pub flush_dns: bool,
+    pub hooks: HookSettings,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApplyReport {
+    pub flush_summary: Option<String>,
+    pub hook_runs: Vec<HookRun>,
+}
+
+// Anche in caso di errore gli hook già eseguiti finiscono nel pannello dei log
+#[derive(Debug, Clone)]
+pub struct ApplyError {
+    pub message: String,
+    pub permission_denied: bool,
+    pub hook_runs: Vec<HookRun>,
+}
+
+// Applica un profilo: validazione, hook pre-apply, scrittura del file hosts seguita dallo
+// svuotamento delle cache DNS, conferma della modifica al database con `commit` e hook post-apply.
+// Nessuna transazione resta aperta durante gli hook o la richiesta della password: `commit`
+// parte solo dopo una scrittura riuscita e, se fallisce, viene ripristinato il file precedente.
+pub fn apply_and_commit<L, F>(entries: &[L], options: &ApplyOptions, commit: F) -> Result<ApplyReport, ApplyError>
+where
+    L: Borrow<Line>,
+    F: FnOnce() -> Result<(), String>,
+{
+    // Le righe che l'helper rifiuterebbe vengono segnalate prima di eseguire hook o chiedere la password
+    validate_lines(entries).map_err(|message| ApplyError { message, permission_denied: false, hook_runs: Vec::new() })?;
+
+    let path = get_hosts_file_path();
+    let content = render_hosts_file(entries);
+    let previous = fs::read_to_string(&path).ok();
+
+    // File già aggiornato: niente scrittura, hook, svuotamento delle cache o richiesta della password
+    if previous.as_deref() == Some(content.as_str()) {
+        commit().map_err(|e| ApplyError {
+            message: format!("Errore nel salvataggio nel database: {}", e),
+            permission_denied: false,
+            hook_runs: Vec::new(),
+        })?;
+        return Ok(ApplyReport { flush_summary: None, hook_runs: Vec::new() });
+    }
+
+    let mut hook_runs = Vec::new();
+
+    let has_hooks = !options.hooks.pre_apply.trim().is_empty() || !options.hooks.post_apply.trim().is_empty();
+    let diff = if has_hooks {
+        hosts_diff(previous.as_deref().unwrap_or_default(), &content)
+    } else {
+        String::new()
+    };
+    let context = HookContext {
+        profile_name: &options.profile_name,
+        target_path: &path,
+        diff: &diff,
+    };
+    let timeout = Duration::from_secs(options.hooks.timeout_secs);
+
+    let pre_apply = options.hooks.pre_apply.trim();
+    if !pre_apply.is_empty() {
+        let run = run_hook(pre_apply, HookStage::PreApply, &context, timeout);
+        let failed = !run.success;
+        hook_runs.push(run);
+        if failed && options.hooks.abort_on_pre_failure {
+            return Err(ApplyError {
+                message: "Hook pre-apply fallito: il file hosts non è stato modificato.".to_string(),
+                permission_denied: false,
+                hook_runs,
+            });
+        }
+    }
+
+    // Le cache DNS vengono svuotate da chi scrive il file, con gli stessi privilegi
+    let flush_outcomes = match write_hosts_lines(entries, options.flush_dns) {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            return Err(ApplyError {
+                message: e.to_string(),
+                permission_denied: matches!(e, WriteError::PermissionDenied(_)),
+                hook_runs,
+            });
+        }
+    };
+
+    if let Err(e) = commit() {
+        // Il file precedente torna identico, alias e formattazione compresi
+        let restored = previous.is_some_and(|content| restore_hosts_content(&content, options.flush_dns).is_ok());
+        return Err(ApplyError {
+            message: format!(
+                "Errore nel salvataggio nel database: {}. {}",
+                e,
+                if restored { "Il file hosts precedente è stato ripristinato." } else { "Il file hosts non è stato ripristinato." }
+            ),
+            permission_denied: false,
+            hook_runs,
+        });
+    }
+
+    // Senza svuotare le cache locali la modifica può non essere visibile subito
+    let flush_summary = options.flush_dns.then(|| summarize_flush(&flush_outcomes));
+
+    let post_apply = options.hooks.post_apply.trim();
+    if !post_apply.is_empty() {
+        hook_runs.push(run_hook(post_apply, HookStage::PostApply, &context, timeout));
+    }
+
+    Ok(ApplyReport { flush_summary, hook_runs })
+}
+
+// Modifica al database da confermare insieme alla scrittura del file hosts
+#[derive(Debug, Clone)]
+pub struct ProfileUpdate {
+    pub profile_id: String,
+    // Righe da scrivere nel file hosts; None per usare quelle salvate nel database
+    pub hosts: Option<Arc<Vec<Line>>>,
+    pub save_hosts: bool,
+    pub activate: bool,
+}
+
+// Salva e/o attiva il profilo e scrive il file hosts come un'unica operazione:
+// la transazione viene aperta e confermata solo dopo una scrittura riuscita
+pub fn apply_profile(update: &ProfileUpdate, sources: &[(String, Arc<Vec<Line>>)], options: &ApplyOptions) -> Result<ApplyReport, ApplyError> {
+    let hosts = match &update.hosts {
+        Some(hosts) => hosts.clone(),
+        None => {
+            let conn = db_manager::initialize_db().map_err(database_error)?;
+            Arc::new(db_manager::get_profile_hosts(&conn, &update.profile_id).map_err(database_error)?)
+        }
+    };
+
+    apply_and_commit(&lines_with_sources(&hosts, sources), options, || {
+        if !update.save_hosts && !update.activate {
+            return Ok(());
+        }
+        let mut conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        if update.save_hosts {
+            db_manager::update_profile_hosts(&tx, &update.profile_id, &hosts).map_err(|e| e.to_string())?;
+        }
+        if update.activate {
+            db_manager::set_active_profile(&tx, &update.profile_id).map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    })
+}
+
+fn database_error(e: rusqlite::Error) -> ApplyError {
+    ApplyError {
+        message: format!("Errore del database: {}. Il file hosts non è stato modificato.", e),
+        permission_denied: false,
+        hook_runs: Vec::new(),
+    }
+}
@@ -0,0 +1,83 @@
+use std::env;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::app::{self, MyApp};
+use crate::db_manager;
+use crate::host_manager::{parse_hosts, render_hosts, write_hosts_entries_to_path, Entry, Line};
+
+const BENCHMARK_LINES: usize = 100_000;
+
+// Avviato con `hosts-manager --benchmark`: misura i passaggi critici su un profilo
+// sintetico da 100k righe, senza toccare /etc/hosts né il database reale.
+pub fn run() -> Result<(), String> {
+    let work_dir = env::temp_dir().join(format!("hosts_manager_bench_{}", std::process::id()));
+    fs::create_dir_all(&work_dir).map_err(|e| e.to_string())?;
+    let result = run_in(&work_dir);
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
+
+fn run_in(work_dir: &std::path::Path) -> Result<(), String> {
+    println!("Benchmark con {} righe", BENCHMARK_LINES);
+
+    let lines = synthetic_lines(BENCHMARK_LINES);
+    let contents = render_hosts(&lines);
+
+    let (parsed, elapsed) = timed(|| parse_hosts(&contents));
+    report("parse", elapsed);
+
+    let (_, elapsed) = timed(|| render_hosts(&parsed));
+    report("render testo", elapsed);
+
+    let hosts_path = work_dir.join("hosts");
+    let (written, elapsed) = timed(|| write_hosts_entries_to_path(&hosts_path, &parsed));
+    written.map_err(|e| e.to_string())?;
+    report("scrittura file hosts", elapsed);
+
+    let conn = db_manager::open_db(&work_dir.join("profiles.db")).map_err(|e| e.to_string())?;
+    db_manager::create_profile(&conn, "benchmark", &parsed).map_err(|e| e.to_string())?;
+    let (profiles, elapsed) = timed(|| db_manager::get_all_profiles(&conn));
+    let profiles = profiles.map_err(|e| e.to_string())?;
+    report("caricamento profilo dal database", elapsed);
+
+    let profile_id = profiles.first().map(|p| p.id.clone()).unwrap_or_default();
+    let (saved, elapsed) = timed(|| db_manager::update_profile_hosts(&conn, &profile_id, &parsed));
+    saved.map_err(|e| e.to_string())?;
+    report("salvataggio profilo nel database", elapsed);
+
+    let state = MyApp {
+        file_lines: Arc::new(parsed),
+        ..MyApp::default()
+    };
+    let (_, elapsed) = timed(|| app::view(&state));
+    report("costruzione vista principale", elapsed);
+
+    Ok(())
+}
+
+fn synthetic_lines(count: usize) -> Vec<Line> {
+    (0..count)
+        .map(|i| match i % 50 {
+            0 => Line::Comment(format!("# blocco {}", i / 50)),
+            49 => Line::Empty,
+            _ => Line::Entry(Entry {
+                ip: if i % 7 == 0 { "0.0.0.0".to_string() } else { format!("10.{}.{}.{}", i >> 16 & 255, i >> 8 & 255, i & 255) },
+                hostname: format!("host-{}.example.com", i),
+                enabled: i % 11 != 0,
+                comment: (i % 13 == 0).then(|| "generato".to_string()),
+            }),
+        })
+        .collect()
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let value = f();
+    (value, start.elapsed())
+}
+
+fn report(step: &str, elapsed: Duration) {
+    println!("{:<36} {:>8.1} ms", step, elapsed.as_secs_f64() * 1000.0);
+}
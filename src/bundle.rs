@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::db_manager::{self, Profile};
+
+// Esportazione completa: tutti i profili, le impostazioni e i metadati per la migrazione su un'altra macchina
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileBundle {
+    pub app_version: String,
+    pub exported_at: u64,
+    pub profiles: Vec<Profile>,
+    #[serde(default)]
+    pub settings: Vec<(String, String)>,
+    // Sorgenti sottoscritte, legate al profilo per nome perché gli id cambiano all'importazione
+    #[serde(default)]
+    pub sources: Vec<BundleSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleSource {
+    pub profile_name: String,
+    pub path: String,
+    pub format: String,
+}
+
+// Impostazioni che restano sulla macchina: gli hook sono comandi eseguiti a ogni
+// applicazione e non devono arrivare da un file importato, lo stato dell'ultima
+// applicazione riguarda solo il file hosts locale
+const LOCAL_SETTINGS: [&str; 2] = [db_manager::HOOK_SETTINGS_KEY, db_manager::NOT_APPLIED_KEY];
+
+fn is_portable_setting(key: &str) -> bool {
+    !LOCAL_SETTINGS.contains(&key)
+}
+
+pub fn build_bundle() -> Result<ProfileBundle, String> {
+    let conn = db_manager::initialize_db().map_err(|e| e.to_string())?;
+    let profiles = db_manager::get_all_profiles(&conn)
+        .map_err(|e| format!("Errore nel caricamento dei profili: {}", e))?;
+    let mut settings = db_manager::get_all_settings(&conn)
+        .map_err(|e| format!("Errore nel caricamento delle impostazioni: {}", e))?;
+    settings.retain(|(key, _)| is_portable_setting(key));
+    let sources = db_manager::get_all_profile_sources(&conn)
+        .map_err(|e| format!("Errore nel caricamento delle sorgenti: {}", e))?
+        .into_iter()
+        .filter_map(|source| {
+            let profile = profiles.iter().find(|p| p.id == source.profile_id)?;
+            Some(BundleSource {
+                profile_name: profile.name.clone(),
+                path: source.path,
+                format: source.format.as_str().to_string(),
+            })
+        })
+        .collect();
+
+    let exported_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    Ok(ProfileBundle {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at,
+        profiles,
+        settings,
+        sources,
+    })
+}
+
+pub fn parse_bundle(json_data: &str) -> Result<ProfileBundle, String> {
+    let mut bundle: ProfileBundle = serde_json::from_str(json_data)
+        .map_err(|e| format!("Errore di deserializzazione del bundle: {}", e))?;
+    // Anche i bundle creati prima dell'esclusione possono contenere gli hook
+    bundle.settings.retain(|(key, _)| is_portable_setting(key));
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bundle_drops_hook_settings() {
+        let json = r#"{
+            "app_version": "0.1.0",
+            "exported_at": 0,
+            "profiles": [],
+            "settings": [["apply_hooks", "{\"pre_apply\":\"rm -rf ~\"}"], ["staged_edits", "1"]]
+        }"#;
+        let bundle = parse_bundle(json).unwrap();
+        assert_eq!(bundle.settings, vec![("staged_edits".to_string(), "1".to_string())]);
+    }
+}
@@ -1,12 +1,16 @@
 use rusqlite::{Connection, Result, params};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
-use serde_json;
 
-use crate::host_manager::Line;
+use crate::bundle::BundleSource;
+use crate::hooks::HookSettings;
+use crate::host_manager::{Entry, Line};
+use crate::sources::{ProfileSource, SourceFormat};
+use std::env;
 use std::fmt;
-use std::path::PathBuf;
-use dirs;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use rusqlite::ffi::Error;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,21 +27,198 @@ impl fmt::Display for Profile {
     }
 }
 
-pub fn initialize_db() -> Result<Connection> {
-    
-    let mut db_path: PathBuf = dirs::data_dir().unwrap_or_else(|| {
+// Variabile d'ambiente e argomento da riga di comando per usare un database in un altro percorso
+pub const DB_PATH_ENV: &str = "HOSTS_MANAGER_DB";
+pub const DB_PATH_FLAG: &str = "--db";
+
+// Dove si trova il database e perché, mostrato nella gestione dei profili
+#[derive(Debug, Clone)]
+pub struct DbLocation {
+    pub path: PathBuf,
+    pub origin: String,
+    // Utente e gruppo a cui assegnare i file creati girando come root per conto di un altro utente
+    owner: Option<(u32, u32)>,
+    // Perché non è stato possibile usare la cartella dell'utente che ha chiesto i privilegi
+    pub warning: Option<String>,
+}
+
+static DB_LOCATION: OnceLock<DbLocation> = OnceLock::new();
+
+pub fn db_location() -> &'static DbLocation {
+    DB_LOCATION.get_or_init(resolve_db_location)
+}
+
+fn resolve_db_location() -> DbLocation {
+    if let Some(path) = cli_db_path() {
+        return DbLocation { path, origin: format!("argomento {}", DB_PATH_FLAG), owner: None, warning: None };
+    }
+    if let Some(path) = env::var_os(DB_PATH_ENV).filter(|p| !p.is_empty()) {
+        return DbLocation { path: PathBuf::from(path), origin: format!("variabile {}", DB_PATH_ENV), owner: None, warning: None };
+    }
+
+    // Con sudo o pkexec dirs::data_dir() punterebbe alla cartella di root e i profili
+    // sembrerebbero spariti: si usa quella dell'utente che ha lanciato il comando
+    let warning = match invoking_user() {
+        Ok(Some(user)) => {
+            let mut path = user_data_dir(&user.home);
+            path.push("hosts_manager");
+            path.push("profiles.db");
+            return DbLocation { path, origin: format!("utente {}", user.name), owner: Some((user.uid, user.gid)), warning: None };
+        }
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("{}", e);
+            Some(e)
+        }
+    };
+
+    let mut path: PathBuf = dirs::data_dir().unwrap_or_else(|| {
         eprintln!("Impossibile trovare la directory dei dati dell'applicazione, verrà usato il percorso locale.");
         PathBuf::from(".")
     });
+    path.push("hosts_manager");
+    path.push("profiles.db");
+    DbLocation { path, origin: "predefinito".to_string(), owner: None, warning }
+}
 
-    db_path.push("hosts_manager");
+// Accetta sia `--db <percorso>` sia `--db=<percorso>`
+fn cli_db_path() -> Option<PathBuf> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == DB_PATH_FLAG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix(DB_PATH_FLAG).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
 
-    std::fs::create_dir_all(&db_path)
-        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+struct InvokingUser {
+    name: String,
+    uid: u32,
+    gid: u32,
+    home: PathBuf,
+}
+
+// L'utente che ha ottenuto i privilegi con sudo (SUDO_USER) o pkexec (PKEXEC_UID), cercato
+// con getpwnam/getpwuid così da trovare anche gli utenti LDAP o di sssd. Solo se il processo
+// gira con uid effettivo 0: USER può arrivare invariato dall'ambiente dell'utente.
+// Se la ricerca fallisce ritorna il messaggio da mostrare come avviso.
+#[cfg(unix)]
+fn invoking_user() -> std::result::Result<Option<InvokingUser>, String> {
+    if unsafe { libc::geteuid() } != 0 {
+        return Ok(None);
+    }
+    let sudo_user = env::var("SUDO_USER").ok().filter(|name| !name.is_empty() && name != "root");
+    let pkexec_uid = env::var("PKEXEC_UID").ok().and_then(|uid| uid.parse::<u32>().ok()).filter(|uid| *uid != 0);
+
+    match (sudo_user, pkexec_uid) {
+        (Some(name), _) => lookup_user_by_name(&name)
+            .map(Some)
+            .ok_or_else(|| format!("Utente '{}' non trovato: viene usato il database di root.", name)),
+        (None, Some(uid)) => lookup_user_by_uid(uid)
+            .map(Some)
+            .ok_or_else(|| format!("Utente con uid {} non trovato: viene usato il database di root.", uid)),
+        (None, None) => Ok(None),
+    }
+}
+
+#[cfg(not(unix))]
+fn invoking_user() -> std::result::Result<Option<InvokingUser>, String> {
+    Ok(None)
+}
+
+#[cfg(unix)]
+fn lookup_user_by_name(name: &str) -> Option<InvokingUser> {
+    let name = std::ffi::CString::new(name).ok()?;
+    lookup_user(|passwd, buffer, result| unsafe {
+        libc::getpwnam_r(name.as_ptr(), passwd, buffer.as_mut_ptr(), buffer.len(), result)
+    })
+}
+
+#[cfg(unix)]
+fn lookup_user_by_uid(uid: u32) -> Option<InvokingUser> {
+    lookup_user(|passwd, buffer, result| unsafe {
+        libc::getpwuid_r(uid, passwd, buffer.as_mut_ptr(), buffer.len(), result)
+    })
+}
+
+// Esegue getpwnam_r o getpwuid_r e copia i campi che servono prima che il buffer venga liberato
+#[cfg(unix)]
+fn lookup_user<F>(query: F) -> Option<InvokingUser>
+where
+    F: Fn(&mut libc::passwd, &mut [libc::c_char], &mut *mut libc::passwd) -> libc::c_int,
+{
+    use std::ffi::{CStr, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut buffer_len = 4096;
+    loop {
+        let mut buffer: Vec<libc::c_char> = vec![0; buffer_len];
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        let status = query(&mut passwd, &mut buffer, &mut result);
+        // Con ERANGE il buffer non basta per la voce: si riprova con uno più grande
+        if status == libc::ERANGE && buffer_len < 1024 * 1024 {
+            buffer_len *= 4;
+            continue;
+        }
+        if status != 0 || result.is_null() {
+            return None;
+        }
 
-    db_path.push("profiles.db");
-    
-    let conn = Connection::open(&db_path)?;
+        let name = unsafe { CStr::from_ptr(passwd.pw_name) }.to_string_lossy().into_owned();
+        let home = PathBuf::from(OsStr::from_bytes(unsafe { CStr::from_ptr(passwd.pw_dir) }.to_bytes()));
+        return Some(InvokingUser { name, uid: passwd.pw_uid, gid: passwd.pw_gid, home });
+    }
+}
+
+// Equivalente di dirs::data_dir() per la home di un altro utente
+fn user_data_dir(home: &Path) -> PathBuf {
+    if env::consts::OS == "macos" {
+        home.join("Library").join("Application Support")
+    } else {
+        home.join(".local").join("share")
+    }
+}
+
+pub fn initialize_db() -> Result<Connection> {
+    let location = db_location();
+    let to_sql_error = |e: std::io::Error| rusqlite::Error::ToSqlConversionFailure(Box::new(e));
+
+    if let Some(dir) = location.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        // Le cartelle mancanti vengono create come root: vanno restituite all'utente
+        let missing: Vec<PathBuf> = dir.ancestors().take_while(|p| !p.exists()).map(Path::to_path_buf).collect();
+        fs::create_dir_all(dir).map_err(to_sql_error)?;
+        if let Some(owner) = location.owner {
+            for path in &missing {
+                set_owner(path, owner).map_err(to_sql_error)?;
+            }
+        }
+    }
+
+    let conn = open_db(&location.path)?;
+    if let Some(owner) = location.owner {
+        set_owner(&location.path, owner).map_err(to_sql_error)?;
+    }
+    Ok(conn)
+}
+
+#[cfg(unix)]
+fn set_owner(path: &Path, (uid, gid): (u32, u32)) -> std::io::Result<()> {
+    std::os::unix::fs::chown(path, Some(uid), Some(gid))
+}
+
+#[cfg(not(unix))]
+fn set_owner(_path: &Path, _owner: (u32, u32)) -> std::io::Result<()> {
+    Ok(())
+}
+
+// Apre (creandolo se serve) un database in un percorso qualsiasi, usato anche dal benchmark
+pub fn open_db(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
    
     conn.execute(
         "CREATE TABLE IF NOT EXISTS profiles (
@@ -49,6 +230,24 @@ pub fn initialize_db() -> Result<Connection> {
         [],
     )?;
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS profile_sources (
+            id TEXT PRIMARY KEY,
+            profile_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            format TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
     Ok(conn)
 }
 
@@ -92,19 +291,57 @@ pub fn set_active_profile(conn: &Connection, profile_id: &str) -> Result<()> {
 
 pub fn delete_profile(conn: &Connection, profile_id: &str) -> Result<()> {
     conn.execute("DELETE FROM profiles WHERE id = ?1", params![profile_id])?;
+    conn.execute("DELETE FROM profile_sources WHERE profile_id = ?1", params![profile_id])?;
     Ok(())
 }
 
 pub fn update_profile(conn: &Connection, profile: &Profile) -> Result<()> {
-    let hosts_json = serde_json::to_string(&profile.hosts).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    update_profile_hosts(conn, &profile.id, &profile.hosts)
+}
+
+pub fn update_profile_hosts(conn: &Connection, profile_id: &str, hosts: &[Line]) -> Result<()> {
+    let hosts_json = serde_json::to_string(hosts).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
 
     conn.execute(
         "UPDATE profiles SET hosts_json = ?1 WHERE id = ?2",
-        params![hosts_json, profile.id],
+        params![hosts_json, profile_id],
     )?;
     Ok(())
 }
 
+pub fn get_profile_hosts(conn: &Connection, profile_id: &str) -> Result<Vec<Line>> {
+    let hosts_json: String = conn.query_row(
+        "SELECT hosts_json FROM profiles WHERE id = ?1",
+        params![profile_id],
+        |row| row.get(0),
+    )?;
+    serde_json::from_str(&hosts_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+// Copia o sposta record in un profilo qualsiasi, anche non attivo, in un'unica transazione:
+// il profilo di destinazione viene letto dal database, quello di origine (se è uno spostamento)
+// viene salvato con le righe rimaste. Ritorna le righe aggiornate della destinazione.
+pub fn transfer_entries(
+    conn: &mut Connection,
+    target_id: &str,
+    lines: &[Line],
+    remaining_source: Option<(&str, &[Line])>,
+) -> Result<Vec<Line>> {
+    let tx = conn.transaction()?;
+
+    let mut target_hosts = get_profile_hosts(&tx, target_id)?;
+    target_hosts.extend_from_slice(lines);
+    update_profile_hosts(&tx, target_id, &target_hosts)?;
+
+    if let Some((source_id, source_hosts)) = remaining_source {
+        update_profile_hosts(&tx, source_id, source_hosts)?;
+    }
+
+    tx.commit()?;
+    Ok(target_hosts)
+}
+
 pub fn import_profile(conn: &Connection, profile: &Profile) -> Result<()> {
     // 1. Controlla se un profilo con lo stesso nome esiste già
     let mut stmt = conn.prepare("SELECT COUNT(*) FROM profiles WHERE name = ?1")?;
@@ -132,4 +369,278 @@ pub fn import_profile(conn: &Connection, profile: &Profile) -> Result<()> {
     )?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn get_setting(conn: &Connection, key: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
+    let mut rows = stmt.query(params![key])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn delete_setting(conn: &Connection, key: &str) -> Result<()> {
+    conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
+    Ok(())
+}
+
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+pub fn get_favourite_dns_servers(conn: &Connection) -> Result<Vec<String>> {
+    match get_setting(conn, "favourite_dns_servers")? {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e))),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn set_favourite_dns_servers(conn: &Connection, servers: &[String]) -> Result<()> {
+    let json = serde_json::to_string(servers).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    set_setting(conn, "favourite_dns_servers", &json)
+}
+
+// Chiave degli hook di applicazione, esclusa dai bundle
+pub const HOOK_SETTINGS_KEY: &str = "apply_hooks";
+
+// Id del profilo attivo la cui ultima scrittura del file hosts è fallita
+pub const NOT_APPLIED_KEY: &str = "hosts_not_applied";
+
+pub fn get_hook_settings(conn: &Connection) -> Result<HookSettings> {
+    match get_setting(conn, HOOK_SETTINGS_KEY)? {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e))),
+        None => Ok(HookSettings::default()),
+    }
+}
+
+pub fn set_hook_settings(conn: &Connection, hooks: &HookSettings) -> Result<()> {
+    let json = serde_json::to_string(hooks).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    set_setting(conn, HOOK_SETTINGS_KEY, &json)
+}
+
+pub fn get_all_settings(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+    let settings_iter = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    settings_iter.collect()
+}
+
+// Record di un profilo che corrisponde alla ricerca globale
+#[derive(Debug, Clone)]
+pub struct HostMatch {
+    pub profile_id: String,
+    pub profile_name: String,
+    pub is_active: bool,
+    pub line_index: usize,
+    pub entry: Entry,
+}
+
+// Cerca un testo in hostname, IP e commenti di tutti i profili, senza distinzione di maiuscole.
+pub fn search_hosts(conn: &Connection, query: &str) -> Result<Vec<HostMatch>> {
+    // Il LIKE sul JSON serve solo a scartare in fretta i profili senza corrispondenze:
+    // SQLite ignora le maiuscole solo per i caratteri ASCII e nel JSON '"' e '\\' sono
+    // preceduti da escape, quindi con altre query si controllano tutti i profili
+    let prefilter = (query.chars().all(|c| c.is_ascii_graphic() || c == ' ') && !query.contains(['"', '\\']))
+        .then(|| format!("%{}%", query.replace('%', "\\%").replace('_', "\\_")));
+    let mut stmt = conn.prepare(
+        "SELECT id, name, hosts_json, is_active FROM profiles WHERE ?1 IS NULL OR hosts_json LIKE ?1 ESCAPE '\\' ORDER BY name",
+    )?;
+    let profiles_iter = stmt.query_map(params![prefilter], |row| {
+        let hosts_json: String = row.get(2)?;
+        let hosts: Vec<Line> = serde_json::from_str(&hosts_json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?;
+        let is_active: i64 = row.get(3)?;
+        Ok(Profile {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            hosts,
+            is_active: is_active != 0,
+        })
+    })?;
+
+    let needle = query.to_lowercase();
+    let mut matches = Vec::new();
+    for profile in profiles_iter {
+        let profile = profile?;
+        for (line_index, line) in profile.hosts.iter().enumerate() {
+            if let Line::Entry(entry) = line {
+                let fields = [entry.hostname.as_str(), entry.ip.as_str(), entry.comment.as_deref().unwrap_or("")];
+                if fields.iter().any(|field| field.to_lowercase().contains(&needle)) {
+                    matches.push(HostMatch {
+                        profile_id: profile.id.clone(),
+                        profile_name: profile.name.clone(),
+                        is_active: profile.is_active,
+                        line_index,
+                        entry: entry.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(matches)
+}
+
+// Azione da eseguire per ciascun profilo di un bundle importato
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportAction {
+    Insert,
+    Skip,
+    Overwrite,
+    Rename(String),
+}
+
+// Importa i profili di un bundle con le loro sorgenti e le impostazioni in un'unica transazione:
+// se un inserimento fallisce (es. nome rinominato ancora duplicato) non viene applicato nulla.
+pub fn apply_bundle_import(
+    conn: &mut Connection,
+    profiles: &[(Profile, ImportAction)],
+    settings: &[(String, String)],
+    sources: &[BundleSource],
+) -> Result<()> {
+    let tx = conn.transaction()?;
+
+    for (profile, action) in profiles {
+        let mut profile_id = None;
+        let hosts_json = serde_json::to_string(&profile.hosts)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+        match action {
+            ImportAction::Skip => {}
+            ImportAction::Insert | ImportAction::Rename(_) => {
+                let name = match action {
+                    ImportAction::Rename(new_name) => new_name,
+                    _ => &profile.name,
+                };
+                let id = Uuid::new_v4().to_string();
+                tx.execute(
+                    "INSERT INTO profiles (id, name, hosts_json, is_active) VALUES (?1, ?2, ?3, 0)",
+                    params![id, name, hosts_json],
+                )?;
+                profile_id = Some(id);
+            }
+            ImportAction::Overwrite => {
+                tx.execute(
+                    "UPDATE profiles SET hosts_json = ?1 WHERE name = ?2",
+                    params![hosts_json, profile.name],
+                )?;
+                profile_id = Some(tx.query_row("SELECT id FROM profiles WHERE name = ?1", params![profile.name], |row| row.get(0))?);
+            }
+        }
+
+        // Le sorgenti già sottoscritte dal profilo non vengono duplicate
+        if let Some(profile_id) = profile_id {
+            for source in sources.iter().filter(|source| source.profile_name == profile.name) {
+                tx.execute(
+                    "INSERT INTO profile_sources (id, profile_id, path, format)
+                     SELECT ?1, ?2, ?3, ?4
+                     WHERE NOT EXISTS (SELECT 1 FROM profile_sources WHERE profile_id = ?2 AND path = ?3)",
+                    params![Uuid::new_v4().to_string(), profile_id, source.path, SourceFormat::from_db(&source.format).as_str()],
+                )?;
+            }
+        }
+    }
+
+    for (key, value) in settings {
+        tx.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+    }
+
+    tx.commit()
+}
+
+pub fn add_profile_source(conn: &Connection, profile_id: &str, path: &str, format: SourceFormat) -> Result<()> {
+    conn.execute(
+        "INSERT INTO profile_sources (id, profile_id, path, format) VALUES (?1, ?2, ?3, ?4)",
+        params![Uuid::new_v4().to_string(), profile_id, path, format.as_str()],
+    )?;
+    Ok(())
+}
+
+pub fn delete_profile_source(conn: &Connection, source_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM profile_sources WHERE id = ?1", params![source_id])?;
+    Ok(())
+}
+
+pub fn get_all_profile_sources(conn: &Connection) -> Result<Vec<ProfileSource>> {
+    let mut stmt = conn.prepare("SELECT id, profile_id, path, format FROM profile_sources")?;
+    let sources_iter = stmt.query_map([], |row| {
+        let format: String = row.get(3)?;
+        Ok(ProfileSource {
+            id: row.get(0)?,
+            profile_id: row.get(1)?,
+            path: row.get(2)?,
+            format: SourceFormat::from_db(&format),
+        })
+    })?;
+    sources_iter.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db_with_comment(comment: &str) -> Connection {
+        let conn = open_db(Path::new(":memory:")).expect("in-memory database");
+        let hosts = [Line::Entry(Entry {
+            ip: "10.0.0.1".to_string(),
+            hostname: "app.local".to_string(),
+            enabled: true,
+            comment: Some(comment.to_string()),
+        })];
+        create_profile(&conn, "Test", &hosts).expect("profile created");
+        conn
+    }
+
+    #[test]
+    fn search_ignores_case_of_non_ascii_text() {
+        let conn = db_with_comment("server in città");
+        assert_eq!(search_hosts(&conn, "CITTÀ").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn search_matches_quotes_and_backslashes() {
+        let conn = db_with_comment(r#"share "dev" \\nas"#);
+        assert_eq!(search_hosts(&conn, "\"dev\"").unwrap().len(), 1);
+        assert_eq!(search_hosts(&conn, r"\\nas").unwrap().len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn looks_up_users_through_passwd_database() {
+        let root = lookup_user_by_uid(0).expect("root user");
+        assert_eq!(root.name, "root");
+        assert_eq!(lookup_user_by_name("root").map(|user| user.uid), Some(0));
+        assert!(lookup_user_by_name("no-such-user-hosts-manager").is_none());
+    }
+
+    #[test]
+    fn bundle_import_keeps_source_subscriptions() {
+        let mut conn = open_db(Path::new(":memory:")).expect("in-memory database");
+        let profile = Profile { id: "old".to_string(), name: "Lavoro".to_string(), hosts: Vec::new(), is_active: false };
+        let sources = [BundleSource { profile_name: "Lavoro".to_string(), path: "/srv/block.txt".to_string(), format: "domains".to_string() }];
+        apply_bundle_import(&mut conn, &[(profile.clone(), ImportAction::Rename("Lavoro 2".to_string()))], &[], &sources).unwrap();
+        apply_bundle_import(&mut conn, &[(profile, ImportAction::Insert)], &[], &sources).unwrap();
+        apply_bundle_import(&mut conn, &[(Profile { id: "old".to_string(), name: "Lavoro".to_string(), hosts: Vec::new(), is_active: false }, ImportAction::Overwrite)], &[], &sources).unwrap();
+
+        let imported = get_all_profile_sources(&conn).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert!(imported.iter().all(|source| source.path == "/srv/block.txt" && source.format == SourceFormat::DomainList));
+    }
+
+    #[test]
+    fn search_uses_prefilter_for_ascii_queries() {
+        let conn = db_with_comment("frontend");
+        assert_eq!(search_hosts(&conn, "FRONT").unwrap().len(), 1);
+        assert!(search_hosts(&conn, "backend").unwrap().is_empty());
+    }
+}
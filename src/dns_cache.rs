@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+// Esito dello svuotamento di una cache DNS locale, passato dall'helper privilegiato alla GUI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlushOutcome {
+    pub cache: String,
+    pub result: Result<(), String>,
+}
+
+// Svuota le cache DNS del sistema operativo dopo la scrittura del file hosts.
+// Su Linux agisce solo sui servizi effettivamente in esecuzione.
+pub fn flush_dns_caches() -> Vec<FlushOutcome> {
+    match env::consts::OS {
+        "windows" => vec![run("Windows DNS Client", "ipconfig", &["/flushdns"])],
+        "macos" => vec![
+            run("dscacheutil", "dscacheutil", &["-flushcache"]),
+            run("mDNSResponder", "killall", &["-HUP", "mDNSResponder"]),
+        ],
+        _ => flush_linux_caches(),
+    }
+}
+
+fn flush_linux_caches() -> Vec<FlushOutcome> {
+    let processes = running_processes();
+    let mut outcomes = Vec::new();
+
+    if processes.contains("systemd-resolve") {
+        let mut outcome = run("systemd-resolved", "resolvectl", &["flush-caches"]);
+        if outcome.result.is_err() {
+            // Versioni di systemd precedenti a resolvectl
+            outcome = run("systemd-resolved", "systemd-resolve", &["--flush-caches"]);
+        }
+        outcomes.push(outcome);
+    }
+    if processes.contains("nscd") {
+        outcomes.push(run("nscd", "nscd", &["-i", "hosts"]));
+    }
+    if processes.contains("dnsmasq") {
+        // SIGHUP fa rileggere /etc/hosts a dnsmasq e ne svuota la cache
+        outcomes.push(run("dnsmasq", "pkill", &["-HUP", "-x", "dnsmasq"]));
+    }
+    outcomes
+}
+
+// Nomi dei processi in esecuzione, letti da /proc/<pid>/comm (troncati a 15 caratteri dal kernel)
+fn running_processes() -> HashSet<String> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return HashSet::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().chars().all(|c| c.is_ascii_digit()))
+        .filter_map(|entry| fs::read_to_string(entry.path().join("comm")).ok())
+        .map(|name| name.trim().to_string())
+        .collect()
+}
+
+fn run(cache: &'static str, program: &str, args: &[&str]) -> FlushOutcome {
+    let result = match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => Err(format!("{}: {}", program, e)),
+    };
+    FlushOutcome { cache: cache.to_string(), result }
+}
+
+// Riepilogo per l'etichetta di stato
+pub fn summarize_flush(outcomes: &[FlushOutcome]) -> String {
+    if outcomes.is_empty() {
+        return "nessuna cache DNS locale rilevata".to_string();
+    }
+    outcomes
+        .iter()
+        .map(|outcome| match &outcome.result {
+            Ok(()) => format!("{} svuotata", outcome.cache),
+            Err(e) if e.is_empty() => format!("{} non svuotata", outcome.cache),
+            Err(e) => format!("{} non svuotata ({})", outcome.cache, e),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
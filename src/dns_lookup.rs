@@ -1,48 +1,213 @@
 use trust_dns_resolver::Resolver;
 use trust_dns_resolver::config::{ResolverConfig, ResolverOpts, NameServerConfig};
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::system_conf::read_system_conf;
 use std::io;
 use std::net::{IpAddr, SocketAddr};
-use std::str::FromStr; 
+use std::str::FromStr;
+use std::time::Instant;
 
 
-pub fn resolve_hostname_with_specific_dns(
+#[derive(Debug, Clone)]
+pub struct LookupRecord {
+    pub name: String,
+    pub record_type: String,
+    pub data: String,
+    pub ttl: u32,
+}
+
+// Dettagli di una risoluzione: catena CNAME, record con TTL, server interrogato e tempo di risposta
+#[derive(Debug, Clone)]
+pub struct LookupDetails {
+    pub hostname: String,
+    pub cname_chain: Vec<String>,
+    pub ips: Vec<String>,
+    pub records: Vec<LookupRecord>,
+    pub server: String,
+    pub elapsed_ms: u128,
+}
+
+pub fn lookup_details_with_specific_dns(
     hostname: &str,
     dns_server_ip_str: &str,
-) -> io::Result<String> {
-    let mut resolver = Resolver::new(ResolverConfig::default(), ResolverOpts::default())?;
-    if !dns_server_ip_str.is_empty() {       
-        let dns_ip = IpAddr::from_str(dns_server_ip_str)
-            .map_err(|e| io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid DNS server IP '{}': {}", dns_server_ip_str, e)
-            ))?;
-    
-        // La porta standard per il DNS è la 53 (UDP)
-        let dns_socket_addr = SocketAddr::new(dns_ip, 53);
-    
-        let mut config = ResolverConfig::new();
-        config.add_name_server(NameServerConfig {
-            socket_addr: dns_socket_addr,
-            protocol: trust_dns_resolver::config::Protocol::Udp, // Il protocollo UDP è il più comune per DNS
-            tls_dns_name: None, // Usa None per DNS su UDP/TCP standard
-            trust_negative_responses: false,
-            bind_addr: None,
-        });
-    
-        resolver = Resolver::new(config, ResolverOpts::default())?;
-       
-    }
+) -> io::Result<LookupDetails> {
+    // Il server vuoto viene sostituito qui con quello di sistema, così l'etichetta
+    // mostra l'indirizzo effettivamente interrogato
+    let dns_server = queried_server(dns_server_ip_str);
+    let resolver = build_resolver(&dns_server, true)?;
+
+    let started = Instant::now();
     let response = resolver.lookup_ip(hostname)
-        .map_err(|e| io::Error::new(
-            io::ErrorKind::Other,
+        .map_err(|e| io::Error::other(
             format!("Failed to resolve hostname '{}': {}", hostname, e)))?;
-    
-    let first_ip = response.iter()
-        .next()
-        .ok_or_else(|| io::Error::new(
+    let elapsed_ms = started.elapsed().as_millis();
+
+    let ips: Vec<String> = response.iter().map(|ip| ip.to_string()).collect();
+    if ips.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No IP addresses found for hostname"
+        ));
+    }
+
+    let records: Vec<LookupRecord> = response.as_lookup()
+        .records()
+        .iter()
+        .filter_map(|record| record.data().map(|data| LookupRecord {
+            name: record.name().to_string().trim_end_matches('.').to_string(),
+            record_type: record.record_type().to_string(),
+            data: data.to_string().trim_end_matches('.').to_string(),
+            ttl: record.ttl(),
+        }))
+        .collect();
+
+    // La catena parte dal nome richiesto e segue i CNAME nell'ordine della risposta
+    let mut cname_chain = vec![hostname.trim_end_matches('.').to_string()];
+    cname_chain.extend(records.iter()
+        .filter(|r| r.record_type == "CNAME")
+        .map(|r| r.data.clone()));
+
+    let server = match IpAddr::from_str(&dns_server) {
+        Ok(ip) => SocketAddr::new(ip, 53).to_string(),
+        Err(_) => "resolver predefinito".to_string(),
+    };
+
+    Ok(LookupDetails {
+        hostname: hostname.to_string(),
+        cname_chain,
+        ips,
+        records,
+        server,
+        elapsed_ms,
+    })
+}
+
+// Server da interrogare: quello indicato oppure il primo resolver di sistema.
+// Ritorna una stringa vuota solo se il sistema non ne ha configurati.
+fn queried_server(dns_server_ip_str: &str) -> String {
+    if !dns_server_ip_str.is_empty() {
+        return dns_server_ip_str.to_string();
+    }
+    system_dns_servers().into_iter().next().unwrap_or_default()
+}
+
+// Crea un resolver che interroga il server indicato (il primo di sistema se la stringa è vuota).
+// Con use_hosts_file = false il file hosts locale viene ignorato, utile per confrontarlo con il DNS reale.
+pub fn build_resolver(dns_server_ip_str: &str, use_hosts_file: bool) -> io::Result<Resolver> {
+    let mut opts = ResolverOpts::default();
+    opts.use_hosts_file = use_hosts_file;
+
+    let dns_server = queried_server(dns_server_ip_str);
+    if dns_server.is_empty() {
+        return Resolver::new(ResolverConfig::default(), opts);
+    }
+
+    let dns_ip = IpAddr::from_str(&dns_server)
+        .map_err(|e| io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid DNS server IP '{}': {}", dns_server, e)
+        ))?;
+
+    // La porta standard per il DNS è la 53 (UDP)
+    let dns_socket_addr = SocketAddr::new(dns_ip, 53);
+
+    let mut config = ResolverConfig::new();
+    config.add_name_server(NameServerConfig {
+        socket_addr: dns_socket_addr,
+        protocol: trust_dns_resolver::config::Protocol::Udp, // Il protocollo UDP è il più comune per DNS
+        tls_dns_name: None, // Usa None per DNS su UDP/TCP standard
+        trust_negative_responses: false,
+        bind_addr: None,
+    });
+
+    Resolver::new(config, opts)
+}
+
+// Ritorna tutti gli IP del nome; NXDOMAIN e risposte vuote diventano ErrorKind::NotFound
+pub fn resolve_all_ips(resolver: &Resolver, hostname: &str) -> io::Result<Vec<String>> {
+    let response = resolver.lookup_ip(hostname)
+        .map_err(|e| match e.kind() {
+            ResolveErrorKind::NoRecordsFound { .. } => io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No records found for hostname '{}'", hostname)),
+            _ => io::Error::other(
+                format!("Failed to resolve hostname '{}': {}", hostname, e)),
+        })?;
+
+    let ips: Vec<String> = response.iter().map(|ip| ip.to_string()).collect();
+    if ips.is_empty() {
+        return Err(io::Error::new(
             io::ErrorKind::NotFound,
             "No IP addresses found for hostname"
+        ));
+    }
+
+    Ok(ips)
+}
+
+// Reverse lookup (PTR): ritorna i nomi candidati per l'IP indicato, senza il punto finale
+pub fn reverse_lookup_with_specific_dns(
+    ip_str: &str,
+    dns_server_ip_str: &str,
+) -> io::Result<Vec<String>> {
+    let ip = IpAddr::from_str(ip_str)
+        .map_err(|e| io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid IP '{}': {}", ip_str, e)
         ))?;
 
-    Ok(first_ip.to_string())
+    let resolver = build_resolver(dns_server_ip_str, false)?;
+    let response = resolver.reverse_lookup(ip)
+        .map_err(|e| match e.kind() {
+            ResolveErrorKind::NoRecordsFound { .. } => io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No PTR records found for '{}'", ip_str)),
+            _ => io::Error::other(
+                format!("Failed to reverse lookup '{}': {}", ip_str, e)),
+        })?;
+
+    let mut hostnames: Vec<String> = response.iter()
+        .map(|ptr| ptr.to_string().trim_end_matches('.').to_string())
+        .collect();
+    hostnames.sort();
+    hostnames.dedup();
+
+    if hostnames.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No PTR records found for IP"
+        ));
+    }
+
+    Ok(hostnames)
+}
+
+// Server pubblici proposti nel menu a tendina oltre a quelli di sistema e ai preferiti
+pub const KNOWN_DNS_SERVERS: [&str; 4] = ["1.1.1.1", "8.8.8.8", "9.9.9.9", "208.67.222.222"];
+
+// Legge i resolver configurati nel sistema. Con systemd-resolved /etc/resolv.conf punta allo
+// stub 127.0.0.53, quindi i server upstream reali vengono letti prima da /run/systemd/resolve.
+pub fn system_dns_servers() -> Vec<String> {
+    let mut servers: Vec<String> = Vec::new();
+
+    #[cfg(unix)]
+    for path in ["/run/systemd/resolve/resolv.conf", "/etc/resolv.conf"] {
+        if let Ok(contents) = std::fs::read(path)
+            && let Ok((config, _)) = trust_dns_resolver::system_conf::parse_resolv_conf(contents) {
+            servers.extend(config.name_servers().iter().map(|ns| ns.socket_addr.ip().to_string()));
+        }
+    }
+
+    if servers.is_empty()
+        && let Ok((config, _)) = read_system_conf() {
+        servers.extend(config.name_servers().iter().map(|ns| ns.socket_addr.ip().to_string()));
+    }
+
+    let mut unique = Vec::new();
+    for server in servers {
+        if !unique.contains(&server) {
+            unique.push(server);
+        }
+    }
+    unique
 }
@@ -0,0 +1,118 @@
+use serde::Serialize;
+use std::io;
+
+use crate::db_manager::Profile;
+use crate::dns_lookup::{build_resolver, resolve_all_ips};
+use crate::exporters::csv_field;
+use crate::host_manager::Line;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftStatus {
+    Matching,
+    Different,
+    NxDomain,
+    Error,
+}
+
+impl DriftStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DriftStatus::Matching => "matching",
+            DriftStatus::Different => "different",
+            DriftStatus::NxDomain => "nxdomain",
+            DriftStatus::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftEntry {
+    pub hostname: String,
+    pub profile_ip: String,
+    pub resolved_ips: Vec<String>,
+    pub status: DriftStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftReport {
+    pub profile_name: String,
+    pub dns_server: String,
+    pub entries: Vec<DriftEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Csv,
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+        }
+    }
+}
+
+// Risolve ogni record abilitato del profilo tramite il server scelto, ignorando il file hosts locale
+pub fn build_drift_report(profile: &Profile, dns_server: &str) -> io::Result<DriftReport> {
+    let resolver = build_resolver(dns_server, false)?;
+
+    let entries = profile.hosts
+        .iter()
+        .filter_map(|line| match line {
+            Line::Entry(entry) if entry.enabled => Some(entry),
+            _ => None,
+        })
+        .map(|entry| {
+            let (resolved_ips, status, error) = match resolve_all_ips(&resolver, &entry.hostname) {
+                Ok(ips) if ips.contains(&entry.ip) => (ips, DriftStatus::Matching, None),
+                Ok(ips) => (ips, DriftStatus::Different, None),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => (Vec::new(), DriftStatus::NxDomain, None),
+                Err(e) => (Vec::new(), DriftStatus::Error, Some(e.to_string())),
+            };
+
+            DriftEntry {
+                hostname: entry.hostname.clone(),
+                profile_ip: entry.ip.clone(),
+                resolved_ips,
+                status,
+                error,
+            }
+        })
+        .collect();
+
+    Ok(DriftReport {
+        profile_name: profile.name.clone(),
+        dns_server: dns_server.to_string(),
+        entries,
+    })
+}
+
+pub fn render_report(report: &DriftReport, format: ReportFormat) -> Result<String, String> {
+    match format {
+        ReportFormat::Json => serde_json::to_string_pretty(report)
+            .map_err(|e| format!("Errore di serializzazione: {}", e)),
+        ReportFormat::Csv => Ok(to_csv(report)),
+    }
+}
+
+fn to_csv(report: &DriftReport) -> String {
+    let mut csv = String::from("hostname,profile_ip,resolved_ips,status,error\n");
+    for entry in &report.entries {
+        let fields = [
+            entry.hostname.as_str(),
+            entry.profile_ip.as_str(),
+            &entry.resolved_ips.join(" "),
+            entry.status.label(),
+            entry.error.as_deref().unwrap_or(""),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
@@ -0,0 +1,75 @@
+use iced::{Alignment, Color, Element, Length, widget::{column, text, button, row, scrollable, container, Space}, Theme, Renderer};
+use iced::widget::Text;
+use crate::app::{Message, MyApp};
+use crate::drift_report::{DriftStatus, ReportFormat};
+
+
+pub fn view(state: &MyApp) -> Element<'_, Message> {
+
+    let status_label: Text<'_, Theme, Renderer> = if let Some(msg) = &state.error_message {
+        text(msg).size(16).color(Color::from_rgb(0.8, 0.2, 0.2))
+    } else if let Some(msg) = &state.success_message {
+        text(msg).size(16).color(Color::from_rgb(0.2, 0.7, 0.2))
+    } else {
+        text("")
+    };
+
+    let (title, rows): (String, Vec<Element<Message>>) = match &state.drift_report {
+        Some(report) => (
+            format!("Profilo: {}  -  DNS: {}", report.profile_name,
+                if report.dns_server.is_empty() { "default" } else { &report.dns_server }),
+            report.entries
+                .iter()
+                .map(|entry| {
+                    let status_color = match entry.status {
+                        DriftStatus::Matching => Color::from_rgb(0.2, 0.7, 0.2),
+                        DriftStatus::Different => Color::from_rgb(0.9, 0.6, 0.1),
+                        DriftStatus::NxDomain => Color::from_rgb(0.8, 0.2, 0.2),
+                        DriftStatus::Error => Color::from_rgb(0.5, 0.5, 0.5),
+                    };
+                    let resolved = match &entry.error {
+                        Some(e) => e.clone(),
+                        None => entry.resolved_ips.join(", "),
+                    };
+
+                    row![
+                        text(&entry.hostname).width(Length::FillPortion(3)),
+                        text(&entry.profile_ip).width(Length::FillPortion(2)),
+                        text(resolved).width(Length::FillPortion(3)),
+                        text(entry.status.label()).color(status_color).width(Length::FillPortion(1)),
+                    ]
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .into()
+                })
+                .collect(),
+        ),
+        None => ("Nessun report disponibile".to_string(), Vec::new()),
+    };
+
+    let scrollable_rows = scrollable(column(rows).spacing(5)).height(Length::Fill);
+
+    let export_row = row![
+        button("Esporta JSON").on_press(Message::ExportDriftReport(ReportFormat::Json)),
+        button("Esporta CSV").on_press(Message::ExportDriftReport(ReportFormat::Csv)),
+        Space::with_width(Length::Fill),
+        button("Torna alla vista principale").on_press(Message::ShowMainView),
+    ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    let content = column![
+        text("Confronto con il DNS").size(30).color(Color::from_rgb(0.1, 0.5, 0.8)),
+        text(title).size(18),
+        status_label,
+        scrollable_rows,
+        export_row,
+    ]
+        .spacing(15)
+        .padding(20);
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
@@ -0,0 +1,218 @@
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::host_manager::{Entry, Line};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatusFilter {
+    #[default]
+    All,
+    Enabled,
+    Disabled,
+}
+
+impl StatusFilter {
+    pub const ALL: [StatusFilter; 3] = [StatusFilter::All, StatusFilter::Enabled, StatusFilter::Disabled];
+}
+
+impl fmt::Display for StatusFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusFilter::All => write!(f, "Tutti gli stati"),
+            StatusFilter::Enabled => write!(f, "Solo abilitati"),
+            StatusFilter::Disabled => write!(f, "Solo disabilitati"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IpVersionFilter {
+    #[default]
+    All,
+    V4,
+    V6,
+}
+
+impl IpVersionFilter {
+    pub const ALL: [IpVersionFilter; 3] = [IpVersionFilter::All, IpVersionFilter::V4, IpVersionFilter::V6];
+}
+
+impl fmt::Display for IpVersionFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpVersionFilter::All => write!(f, "IPv4 e IPv6"),
+            IpVersionFilter::V4 => write!(f, "Solo IPv4"),
+            IpVersionFilter::V6 => write!(f, "Solo IPv6"),
+        }
+    }
+}
+
+// Filtro della lista record. La regex viene compilata una volta sola quando cambia il testo,
+// non a ogni ridisegno della vista.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    pub query: String,
+    pub use_regex: bool,
+    pub status: StatusFilter,
+    pub ip_version: IpVersionFilter,
+    regex: Option<Regex>,
+    pub error: Option<String>,
+}
+
+impl EntryFilter {
+    pub fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.compile();
+    }
+
+    pub fn set_use_regex(&mut self, use_regex: bool) {
+        self.use_regex = use_regex;
+        self.compile();
+    }
+
+    fn compile(&mut self) {
+        self.regex = None;
+        self.error = None;
+        if self.use_regex && !self.query.is_empty() {
+            match RegexBuilder::new(&self.query).case_insensitive(true).build() {
+                Ok(regex) => self.regex = Some(regex),
+                Err(e) => self.error = Some(format!("Regex non valida: {}", e)),
+            }
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.query.is_empty() || self.status != StatusFilter::All || self.ip_version != IpVersionFilter::All
+    }
+
+    pub fn matches(&self, entry: &Entry) -> bool {
+        let status_ok = match self.status {
+            StatusFilter::All => true,
+            StatusFilter::Enabled => entry.enabled,
+            StatusFilter::Disabled => !entry.enabled,
+        };
+        let version_ok = match (self.ip_version, IpAddr::from_str(&entry.ip)) {
+            (IpVersionFilter::All, _) => true,
+            (IpVersionFilter::V4, Ok(ip)) => ip.is_ipv4(),
+            (IpVersionFilter::V6, Ok(ip)) => ip.is_ipv6(),
+            (_, Err(_)) => false,
+        };
+        status_ok && version_ok && self.matches_text(entry)
+    }
+
+    // Cerca nel testo di IP, hostname e commento, senza distinzione tra maiuscole e minuscole
+    fn matches_text(&self, entry: &Entry) -> bool {
+        if self.query.is_empty() {
+            return true;
+        }
+        let fields = [entry.ip.as_str(), entry.hostname.as_str(), entry.comment.as_deref().unwrap_or("")];
+        if self.use_regex {
+            // Con una regex non valida non viene mostrato nulla, oltre al messaggio di errore
+            return match &self.regex {
+                Some(regex) => fields.iter().any(|field| regex.is_match(field)),
+                None => false,
+            };
+        }
+        let needle = self.query.to_lowercase();
+        fields.iter().any(|field| field.to_lowercase().contains(&needle))
+    }
+
+    // Indici reali (in `lines`) delle righe da mostrare: modifica ed eliminazione
+    // continuano a lavorare sulla riga giusta anche con il filtro attivo.
+    // Senza filtro vengono mostrati anche commenti, righe vuote e intestazioni di sezione,
+    // saltando il contenuto delle sezioni compresse.
+    pub fn visible_indices(&self, lines: &[Line], collapsed: &HashSet<String>) -> Vec<usize> {
+        if !self.is_active() {
+            let mut hidden = false;
+            return lines
+                .iter()
+                .enumerate()
+                .filter_map(|(index, line)| {
+                    if let Line::Section(name) = line {
+                        hidden = collapsed.contains(name);
+                        return Some(index);
+                    }
+                    (!hidden).then_some(index)
+                })
+                .collect();
+        }
+        lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| match line {
+                Line::Entry(entry) if self.matches(entry) => Some(index),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ip: &str, hostname: &str, enabled: bool, comment: Option<&str>) -> Line {
+        Line::Entry(Entry { ip: ip.to_string(), hostname: hostname.to_string(), enabled, comment: comment.map(str::to_string) })
+    }
+
+    fn sample() -> Vec<Line> {
+        vec![
+            Line::Comment("# intestazione".to_string()),
+            entry("10.0.0.1", "api.local", true, None),
+            Line::Section("Staging".to_string()),
+            entry("10.0.0.2", "api.staging", false, Some("Vecchio server")),
+            Line::Empty,
+            entry("fd00::2", "db.staging", true, None),
+            Line::Section("Prod".to_string()),
+            entry("10.0.1.1", "web.prod", true, None),
+        ]
+    }
+
+    fn filter(query: &str, use_regex: bool) -> EntryFilter {
+        let mut filter = EntryFilter::default();
+        filter.set_use_regex(use_regex);
+        filter.set_query(query.to_string());
+        filter
+    }
+
+    #[test]
+    fn substring_matches_fields_without_case() {
+        let lines = sample();
+        assert_eq!(filter("STAGING", false).visible_indices(&lines, &HashSet::new()), [3, 5]);
+        assert_eq!(filter("vecchio", false).visible_indices(&lines, &HashSet::new()), [3]);
+        // Senza regex i caratteri speciali valgono come testo
+        assert!(filter("api.*", false).visible_indices(&lines, &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn regex_matches_and_reports_invalid_patterns() {
+        let lines = sample();
+        assert_eq!(filter(r"^api\.", true).visible_indices(&lines, &HashSet::new()), [1, 3]);
+        let invalid = filter("api(", true);
+        assert!(invalid.error.is_some());
+        assert!(invalid.visible_indices(&lines, &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn filters_by_status_and_ip_version() {
+        let lines = sample();
+        let mut filter = EntryFilter { status: StatusFilter::Disabled, ..EntryFilter::default() };
+        assert_eq!(filter.visible_indices(&lines, &HashSet::new()), [3]);
+        filter.status = StatusFilter::Enabled;
+        filter.ip_version = IpVersionFilter::V6;
+        assert_eq!(filter.visible_indices(&lines, &HashSet::new()), [5]);
+        filter.ip_version = IpVersionFilter::V4;
+        assert_eq!(filter.visible_indices(&lines, &HashSet::new()), [1, 7]);
+    }
+
+    #[test]
+    fn without_filter_collapsed_sections_hide_their_lines() {
+        let lines = sample();
+        let collapsed = HashSet::from(["Staging".to_string()]);
+        assert_eq!(EntryFilter::default().visible_indices(&lines, &HashSet::new()), [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(EntryFilter::default().visible_indices(&lines, &collapsed), [0, 1, 2, 6, 7]);
+    }
+}
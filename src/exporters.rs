@@ -0,0 +1,234 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::db_manager::Profile;
+use crate::host_manager::{render_hosts, Entry, Line};
+
+// Formati di esportazione disponibili. Per aggiungerne uno basta un nuovo variant
+// con estensione, etichetta e la relativa funzione di rendering in `export_profile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Hosts,
+    Csv,
+    Yaml,
+    Dnsmasq,
+    Unbound,
+    CoreDns,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 7] = [
+        ExportFormat::Json,
+        ExportFormat::Hosts,
+        ExportFormat::Csv,
+        ExportFormat::Yaml,
+        ExportFormat::Dnsmasq,
+        ExportFormat::Unbound,
+        ExportFormat::CoreDns,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Hosts => "hosts",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Yaml => "yaml",
+            ExportFormat::Dnsmasq => "conf",
+            ExportFormat::Unbound => "conf",
+            ExportFormat::CoreDns => "corefile",
+        }
+    }
+
+    // Suffisso del nome file proposto, per distinguere i formati che condividono l'estensione
+    pub fn file_name(&self, profile_name: &str) -> String {
+        match self {
+            ExportFormat::Dnsmasq => format!("{}.dnsmasq.conf", profile_name),
+            ExportFormat::Unbound => format!("{}.unbound.conf", profile_name),
+            _ => format!("{}.{}", profile_name, self.extension()),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            ExportFormat::Json => "JSON (profilo)",
+            ExportFormat::Hosts => "File hosts",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Yaml => "YAML",
+            ExportFormat::Dnsmasq => "dnsmasq",
+            ExportFormat::Unbound => "unbound",
+            ExportFormat::CoreDns => "CoreDNS hosts",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+pub fn export_profile(profile: &Profile, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(profile)
+            .map_err(|e| format!("Errore di serializzazione: {}", e)),
+        ExportFormat::Hosts => Ok(render_hosts(&profile.hosts)),
+        ExportFormat::Csv => Ok(to_csv(profile)),
+        ExportFormat::Yaml => Ok(to_yaml(profile)),
+        ExportFormat::Dnsmasq => Ok(to_dnsmasq(profile)),
+        ExportFormat::Unbound => Ok(to_unbound(profile)),
+        ExportFormat::CoreDns => Ok(to_coredns(profile)),
+    }
+}
+
+fn entries(profile: &Profile) -> impl Iterator<Item = &Entry> {
+    profile.hosts.iter().filter_map(|line| match line {
+        Line::Entry(entry) => Some(entry),
+        _ => None,
+    })
+}
+
+// I formati per i resolver contengono solo i record abilitati
+fn enabled_entries(profile: &Profile) -> impl Iterator<Item = &Entry> {
+    entries(profile).filter(|entry| entry.enabled)
+}
+
+fn to_csv(profile: &Profile) -> String {
+    let mut csv = String::from("ip,hostname,enabled,comment\n");
+    for entry in entries(profile) {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_field(&entry.ip),
+            csv_field(&entry.hostname),
+            entry.enabled,
+            csv_field(entry.comment.as_deref().unwrap_or("")),
+        ));
+    }
+    csv
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_yaml(profile: &Profile) -> String {
+    let mut yaml = format!("name: {}\nhosts:\n", yaml_string(&profile.name));
+    for entry in entries(profile) {
+        yaml.push_str(&format!("  - ip: {}\n", yaml_string(&entry.ip)));
+        yaml.push_str(&format!("    hostname: {}\n", yaml_string(&entry.hostname)));
+        yaml.push_str(&format!("    enabled: {}\n", entry.enabled));
+        if let Some(comment) = &entry.comment {
+            yaml.push_str(&format!("    comment: {}\n", yaml_string(comment)));
+        }
+    }
+    yaml
+}
+
+// Stringhe sempre tra doppi apici: evita ambiguità con numeri, booleani e caratteri speciali
+fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn to_dnsmasq(profile: &Profile) -> String {
+    let mut conf = format!("# dnsmasq overrides for profile '{}'\n", profile.name);
+    for entry in enabled_entries(profile) {
+        conf.push_str(&format!("address=/{}/{}\n", entry.hostname, entry.ip));
+    }
+    conf
+}
+
+fn to_unbound(profile: &Profile) -> String {
+    let mut conf = format!("# unbound overrides for profile '{}'\nserver:\n", profile.name);
+    for entry in enabled_entries(profile) {
+        let record_type = match IpAddr::from_str(&entry.ip) {
+            Ok(IpAddr::V6(_)) => "AAAA",
+            _ => "A",
+        };
+        conf.push_str(&format!("    local-data: \"{}. IN {} {}\"\n", entry.hostname, record_type, entry.ip));
+    }
+    conf
+}
+
+fn to_coredns(profile: &Profile) -> String {
+    let mut conf = format!("# CoreDNS hosts block for profile '{}'\nhosts {{\n", profile.name);
+    for entry in enabled_entries(profile) {
+        conf.push_str(&format!("    {} {}\n", entry.ip, entry.hostname));
+    }
+    conf.push_str("    fallthrough\n}\n");
+    conf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Profilo di riferimento: record abilitati e disabilitati, commenti, sezioni e IPv6
+    fn fixture() -> Profile {
+        let entry = |ip: &str, hostname: &str, enabled: bool, comment: Option<&str>| {
+            Line::Entry(Entry {
+                ip: ip.to_string(),
+                hostname: hostname.to_string(),
+                enabled,
+                comment: comment.map(str::to_string),
+            })
+        };
+        Profile {
+            id: "fixture-id".to_string(),
+            name: "Fixture".to_string(),
+            hosts: vec![
+                Line::Comment("# Ambiente di sviluppo".to_string()),
+                Line::Section("web".to_string()),
+                entry("10.0.0.1", "app.local", true, Some("frontend")),
+                entry("10.0.0.2", "api.local", false, None),
+                Line::Empty,
+                Line::Section("ipv6".to_string()),
+                entry("::1", "ip6.local", true, None),
+                entry("fd00::10", "db.local", true, Some("primario, \"rw\"")),
+            ],
+            is_active: false,
+        }
+    }
+
+    fn assert_golden(format: ExportFormat, expected: &str) {
+        let rendered = export_profile(&fixture(), format).expect("export should succeed");
+        assert_eq!(rendered, expected, "formato {}", format);
+    }
+
+    #[test]
+    fn exports_json() {
+        assert_golden(ExportFormat::Json, include_str!("../tests/golden/exporters/profile.json"));
+    }
+
+    #[test]
+    fn exports_hosts() {
+        assert_golden(ExportFormat::Hosts, include_str!("../tests/golden/exporters/profile.hosts"));
+    }
+
+    #[test]
+    fn exports_csv() {
+        assert_golden(ExportFormat::Csv, include_str!("../tests/golden/exporters/profile.csv"));
+    }
+
+    #[test]
+    fn exports_yaml() {
+        assert_golden(ExportFormat::Yaml, include_str!("../tests/golden/exporters/profile.yaml"));
+    }
+
+    #[test]
+    fn exports_dnsmasq() {
+        assert_golden(ExportFormat::Dnsmasq, include_str!("../tests/golden/exporters/profile.dnsmasq"));
+    }
+
+    #[test]
+    fn exports_unbound() {
+        assert_golden(ExportFormat::Unbound, include_str!("../tests/golden/exporters/profile.unbound"));
+    }
+
+    #[test]
+    fn exports_coredns() {
+        assert_golden(ExportFormat::CoreDns, include_str!("../tests/golden/exporters/profile.corefile"));
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::host_manager::Line;
+
+pub const DEFAULT_PORTS: &str = "80,443";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_PARALLEL_CHECKS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    Reachable,
+    Unreachable,
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthResult {
+    pub status: HealthStatus,
+    // Prima porta che ha accettato la connessione
+    pub port: Option<u16>,
+    pub latency_ms: Option<u128>,
+}
+
+pub fn parse_ports(input: &str) -> Result<Vec<u16>, String> {
+    let ports: Vec<u16> = input
+        .split([',', ' '])
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| p.trim().parse::<u16>().map_err(|_| format!("Porta non valida: '{}'", p.trim())))
+        .collect::<Result<_, _>>()?;
+
+    if ports.is_empty() {
+        return Err("Indicare almeno una porta.".to_string());
+    }
+    Ok(ports)
+}
+
+// Prova una connessione TCP su ciascuna porta, fermandosi alla prima che risponde
+pub fn check_ip(ip: IpAddr, ports: &[u16]) -> HealthResult {
+    for port in ports {
+        let started = Instant::now();
+        if TcpStream::connect_timeout(&SocketAddr::new(ip, *port), CONNECT_TIMEOUT).is_ok() {
+            return HealthResult {
+                status: HealthStatus::Reachable,
+                port: Some(*port),
+                latency_ms: Some(started.elapsed().as_millis()),
+            };
+        }
+    }
+
+    HealthResult {
+        status: HealthStatus::Unreachable,
+        port: None,
+        latency_ms: None,
+    }
+}
+
+// Controlla gli IP distinti dei record abilitati. Gli indirizzi non specificati (0.0.0.0, ::),
+// tipici delle blocklist, vengono saltati. Il risultato è indicizzato per IP.
+pub fn check_entries(lines: &[Line], ports: &[u16]) -> HashMap<String, HealthResult> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut ips: Vec<(String, IpAddr)> = Vec::new();
+    for line in lines {
+        if let Line::Entry(entry) = line
+            && entry.enabled
+            && let Ok(ip) = IpAddr::from_str(&entry.ip)
+            && !ip.is_unspecified()
+            && seen.insert(entry.ip.as_str()) {
+            ips.push((entry.ip.clone(), ip));
+        }
+    }
+
+    let mut results = HashMap::new();
+    for chunk in ips.chunks(MAX_PARALLEL_CHECKS) {
+        thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|(ip_str, ip)| scope.spawn(move || (ip_str.clone(), check_ip(*ip, ports))))
+                .collect();
+            for handle in handles {
+                if let Ok((ip_str, result)) = handle.join() {
+                    results.insert(ip_str, result);
+                }
+            }
+        });
+    }
+    results
+}
+
+// Ritorna (raggiungibili, controllati) per i record abilitati di un profilo, se almeno uno è stato controllato
+pub fn summarize(lines: &[Line], results: &HashMap<String, HealthResult>) -> Option<(usize, usize)> {
+    let mut reachable = 0;
+    let mut checked = 0;
+    for line in lines {
+        if let Line::Entry(entry) = line
+            && entry.enabled
+            && let Some(result) = results.get(&entry.ip) {
+            checked += 1;
+            if result.status == HealthStatus::Reachable {
+                reachable += 1;
+            }
+        }
+    }
+
+    if checked == 0 { None } else { Some((reachable, checked)) }
+}
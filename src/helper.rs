@@ -0,0 +1,436 @@
+use std::borrow::Borrow;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dns_cache::{flush_dns_caches, FlushOutcome};
+use crate::host_manager::{get_hosts_file_path, is_valid_hostname, is_valid_ip, parse_hosts, render_hosts_file, Line};
+
+// Argomento con cui il binario parte come helper privilegiato invece che come GUI
+pub const HELPER_FLAG: &str = "--apply-helper";
+
+// Un file hosts da 100k record in JSON pesa pochi MB: oltre questo limite l'input viene rifiutato
+const MAX_PAYLOAD_BYTES: u64 = 64 * 1024 * 1024;
+
+// Richiesta passata all'helper. In entrambi i casi si indica se svuotare poi le cache DNS,
+// che senza privilegi non si possono svuotare.
+#[derive(Debug, Serialize, Deserialize)]
+enum HelperRequest {
+    // Scrive le righe validate, salvando prima una copia del file attuale
+    Write { lines: Vec<Line>, flush_dns: bool },
+    // Rimette il file salvato dall'ultima scrittura, byte per byte: il contenuto
+    // non arriva dall'input, quindi il ripristino non aggira la validazione
+    Restore { flush_dns: bool },
+}
+
+// Copia del file hosts precedente all'ultima scrittura dell'helper, accanto al file stesso
+fn backup_path() -> PathBuf {
+    get_hosts_file_path().with_file_name(".hosts-manager.bak")
+}
+
+// Punto d'ingresso dell'helper: legge la richiesta in JSON dallo stdin, valida le righe, scrive
+// il file hosts in modo atomico e riporta sullo stdout l'esito dello svuotamento delle cache DNS.
+// Il percorso di destinazione non arriva mai dall'input.
+pub fn run() -> Result<(), String> {
+    let mut payload = String::new();
+    io::stdin()
+        .take(MAX_PAYLOAD_BYTES + 1)
+        .read_to_string(&mut payload)
+        .map_err(|e| format!("Lettura dello stdin fallita: {}", e))?;
+    if payload.len() as u64 > MAX_PAYLOAD_BYTES {
+        return Err(format!("Input oltre il limite di {} byte", MAX_PAYLOAD_BYTES));
+    }
+
+    let request: HelperRequest = serde_json::from_str(&payload).map_err(|e| format!("Input non valido: {}", e))?;
+    let path = get_hosts_file_path();
+    let flush_dns = match request {
+        HelperRequest::Write { lines, flush_dns } => {
+            validate_lines(&lines)?;
+            match fs::read_to_string(&path) {
+                Ok(previous) => write_atomically(&backup_path(), &previous).map_err(|e| format!("Copia del file hosts fallita: {}", e))?,
+                // Senza file precedente non c'è nulla da ripristinare
+                Err(_) => {
+                    let _ = fs::remove_file(backup_path());
+                }
+            }
+            write_atomically(&path, &render_hosts_file(&lines)).map_err(|e| e.to_string())?;
+            flush_dns
+        }
+        HelperRequest::Restore { flush_dns } => {
+            let previous = fs::read_to_string(backup_path()).map_err(|e| format!("Copia del file hosts non trovata: {}", e))?;
+            write_atomically(&path, &previous).map_err(|e| e.to_string())?;
+            let _ = fs::remove_file(backup_path());
+            flush_dns
+        }
+    };
+
+    let outcomes = if flush_dns { flush_dns_caches() } else { Vec::new() };
+    let output = serde_json::to_string(&outcomes).map_err(|e| e.to_string())?;
+    println!("{}", output);
+    Ok(())
+}
+
+// Controlla che ogni riga produca esattamente la stessa riga una volta riletta:
+// nessun carattere di controllo, IP e hostname validi, commenti che non nascondono record
+pub fn validate_lines<L: Borrow<Line>>(lines: &[L]) -> Result<(), String> {
+    for (index, line) in lines.iter().enumerate() {
+        validate_line(line.borrow()).map_err(|e| format!("Riga {}: {}", index + 1, e))?;
+    }
+    Ok(())
+}
+
+fn validate_line(line: &Line) -> Result<(), String> {
+    match line {
+        Line::Empty => Ok(()),
+        Line::Section(name) => check_text(name),
+        Line::Comment(comment) => {
+            check_text(comment)?;
+            match parse_hosts(comment).first() {
+                Some(Line::Entry(entry)) if entry.enabled => {
+                    Err(format!("il commento '{}' verrebbe letto come record attivo", comment))
+                }
+                _ => Ok(()),
+            }
+        }
+        Line::Entry(entry) => {
+            check_text(&entry.ip)?;
+            check_text(&entry.hostname)?;
+            if let Some(comment) = &entry.comment {
+                check_text(comment)?;
+            }
+            // Anche i commenti dei file hosts di serie (es. "# The following lines are desirable...")
+            // vengono letti come record disattivati: restano commentati, quindi basta il controllo sopra
+            if !entry.enabled {
+                return Ok(());
+            }
+            if !is_valid_ip(&entry.ip) {
+                return Err(format!("IP non valido '{}'", entry.ip));
+            }
+            if !is_valid_hostname(&entry.hostname) {
+                return Err(format!("hostname non valido '{}'", entry.hostname));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn check_text(value: &str) -> Result<(), String> {
+    if value.chars().any(|c| c.is_control() && c != '\t') {
+        return Err("contiene caratteri di controllo".to_string());
+    }
+    Ok(())
+}
+
+// Scrive su un file temporaneo nella stessa cartella e lo rinomina sul file hosts:
+// chi legge vede sempre il file vecchio o quello nuovo completo, mai uno scritto a metà
+pub fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    if fs::rename(&temp_path, path).is_err() {
+        // Nei container /etc/hosts è spesso montato direttamente e non si può sostituire:
+        // in quel caso si ripiega sulla scrittura sul posto
+        let _ = fs::remove_file(&temp_path);
+        return fs::write(path, content.as_bytes());
+    }
+    Ok(())
+}
+
+// Esito di una scrittura fallita: il permesso negato si distingue perché si può
+// riprovare autenticandosi, gli altri errori no
+#[derive(Debug, Clone)]
+pub enum WriteError {
+    PermissionDenied(String),
+    Failed(String),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::PermissionDenied(detail) => write!(f, "Permesso negato sul file hosts ({})", detail),
+            WriteError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// Scrive il file hosts direttamente se il processo ne ha i permessi, altrimenti
+// passa le righe all'helper privilegiato: la GUI può così girare come utente normale.
+// Con `flush_dns` svuota poi le cache DNS con gli stessi privilegi e ne ritorna l'esito.
+pub fn write_hosts_lines<L: Borrow<Line>>(entries: &[L], flush_dns: bool) -> Result<Vec<FlushOutcome>, WriteError> {
+    match write_atomically(&get_hosts_file_path(), &render_hosts_file(entries)) {
+        Ok(()) => Ok(if flush_dns { flush_dns_caches() } else { Vec::new() }),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied && !cfg!(windows) => write_via_helper(entries, flush_dns),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Err(WriteError::PermissionDenied(e.to_string())),
+        Err(e) => Err(WriteError::Failed(e.to_string())),
+    }
+}
+
+// Rimette il file hosts com'era prima di una scrittura, senza rileggerlo né riformattarlo
+pub fn restore_hosts_content(previous: &str, flush_dns: bool) -> Result<Vec<FlushOutcome>, WriteError> {
+    match write_atomically(&get_hosts_file_path(), previous) {
+        Ok(()) => Ok(if flush_dns { flush_dns_caches() } else { Vec::new() }),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied && !cfg!(windows) => run_helper(&HelperRequest::Restore { flush_dns }),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Err(WriteError::PermissionDenied(e.to_string())),
+        Err(e) => Err(WriteError::Failed(e.to_string())),
+    }
+}
+
+fn write_via_helper<L: Borrow<Line>>(entries: &[L], flush_dns: bool) -> Result<Vec<FlushOutcome>, WriteError> {
+    let lines: Vec<Line> = entries.iter().map(|line| line.borrow().clone()).collect();
+    // Le righe che l'helper rifiuterebbe vengono segnalate prima di chiedere la password:
+    // così ogni errore dell'helper riguarda l'elevazione dei privilegi
+    validate_lines(&lines).map_err(WriteError::Failed)?;
+    run_helper(&HelperRequest::Write { lines, flush_dns })
+}
+
+// Lato GUI: rilancia questo stesso binario come helper con i privilegi di amministratore
+// (pkexec se disponibile, altrimenti sudo senza richiesta di password; su macOS la finestra
+// di autenticazione di sistema tramite osascript) e gli passa la richiesta
+fn run_helper(request: &HelperRequest) -> Result<Vec<FlushOutcome>, WriteError> {
+    let payload = serde_json::to_vec(request).map_err(|e| WriteError::Failed(e.to_string()))?;
+    let exe = env::current_exe().map_err(|e| WriteError::Failed(format!("Percorso dell'eseguibile non trovato: {}", e)))?;
+
+    if cfg!(target_os = "macos") {
+        return write_via_osascript(&exe, &payload);
+    }
+
+    let mut last_error = String::from("Nessun metodo di elevazione disponibile (pkexec o sudo)");
+    for (program, args) in [("pkexec", &[][..]), ("sudo", &["-n"][..])] {
+        let spawned = Command::new(program)
+            .args(args)
+            .arg(&exe)
+            .arg(HELPER_FLAG)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(WriteError::Failed(format!("{}: {}", program, e))),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            // Se l'autenticazione viene annullata l'helper non legge lo stdin:
+            // l'errore utile è quello riportato all'uscita, non la pipe chiusa
+            let _ = stdin.write_all(&payload);
+        }
+        let output = child.wait_with_output().map_err(|e| WriteError::Failed(format!("{}: {}", program, e)))?;
+        if output.status.success() {
+            return Ok(flush_outcomes(&output.stdout));
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        last_error = format!("{}: {}", program, stderr.trim());
+        if !is_auth_failure(program, output.status.code(), &stderr) {
+            // L'helper è partito con i privilegi ma la scrittura non è riuscita
+            return Err(WriteError::Failed(last_error));
+        }
+        // pkexec esce con 126 se l'utente annulla la richiesta: non si ripiega su sudo
+        if program == "pkexec" && output.status.code() == Some(126) {
+            break;
+        }
+    }
+    Err(WriteError::PermissionDenied(last_error))
+}
+
+// Esito dello svuotamento riportato dall'helper; un'uscita non leggibile vale come nessuna cache
+fn flush_outcomes(stdout: &[u8]) -> Vec<FlushOutcome> {
+    serde_json::from_slice(stdout).unwrap_or_default()
+}
+
+// Solo un'autenticazione annullata o fallita vale come permesso negato, da riprovare:
+// pkexec esce con 126 o 127, sudo -n segnala la password mancante, osascript riporta
+// l'annullamento (-128) o l'autorizzazione negata (-60005)
+fn is_auth_failure(program: &str, code: Option<i32>, stderr: &str) -> bool {
+    match program {
+        "pkexec" => matches!(code, Some(126 | 127)),
+        "sudo" => stderr.contains("a password is required"),
+        "osascript" => stderr.contains("(-128)") || stderr.contains("(-60005)"),
+        _ => false,
+    }
+}
+
+// macOS non ha pkexec e sudo non può chiedere la password a un'app senza terminale:
+// "do shell script ... with administrator privileges" mostra la finestra di sistema.
+// osascript non inoltra lo stdin, quindi le righe passano da un file temporaneo leggibile
+// solo dall'utente.
+fn write_via_osascript(exe: &Path, payload: &[u8]) -> Result<Vec<FlushOutcome>, WriteError> {
+    let payload_path = env::temp_dir().join(format!("hosts-manager-{}.json", std::process::id()));
+    // Un file rimasto da un'esecuzione interrotta impedirebbe la creazione esclusiva
+    let _ = fs::remove_file(&payload_path);
+    write_private_file(&payload_path, payload).map_err(|e| WriteError::Failed(format!("File temporaneo non scrivibile: {}", e)))?;
+
+    let command = format!(
+        "{} {} < {}",
+        shell_quote(&exe.to_string_lossy()),
+        HELPER_FLAG,
+        shell_quote(&payload_path.to_string_lossy())
+    );
+    let script = format!("do shell script \"{}\" with administrator privileges", applescript_escape(&command));
+    let output = Command::new("osascript").arg("-e").arg(&script).output();
+    let _ = fs::remove_file(&payload_path);
+
+    let output = output.map_err(|e| WriteError::Failed(format!("osascript: {}", e)))?;
+    if output.status.success() {
+        return Ok(flush_outcomes(&output.stdout));
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = format!("osascript: {}", stderr.trim());
+    if is_auth_failure("osascript", output.status.code(), &stderr) {
+        Err(WriteError::PermissionDenied(message))
+    } else {
+        Err(WriteError::Failed(message))
+    }
+}
+
+fn write_private_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content)
+}
+
+// Racchiude il valore tra apici singoli per la shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Rende il valore utilizzabile dentro una stringa AppleScript tra virgolette
+fn applescript_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host_manager::Entry;
+
+    fn entry(ip: &str, hostname: &str, comment: Option<&str>) -> Line {
+        Line::Entry(Entry {
+            ip: ip.to_string(),
+            hostname: hostname.to_string(),
+            enabled: true,
+            comment: comment.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn accepts_valid_lines() {
+        let lines = vec![
+            Line::Section("Sviluppo".to_string()),
+            entry("10.0.0.1", "app.local", Some("api\tinterna")),
+            Line::Comment("# nota".to_string()),
+            Line::Empty,
+        ];
+        assert!(validate_lines(&lines).is_ok());
+    }
+
+    // /etc/hosts di serie di Debian/Ubuntu e di Arch, con i commenti letti come record disattivati
+    const DEBIAN_HOSTS: &str = "127.0.0.1\tlocalhost
+127.0.1.1\tdebian
+
+# The following lines are desirable for IPv6 capable hosts
+::1     localhost ip6-localhost ip6-loopback
+ff02::1 ip6-allnodes
+ff02::2 ip6-allrouters
+";
+    const ARCH_HOSTS: &str = "# Static table lookup for hostnames.
+# See hosts(5) for details.
+";
+
+    #[test]
+    fn accepts_stock_distro_hosts_files() {
+        assert!(validate_lines(&parse_hosts(DEBIAN_HOSTS)).is_ok());
+        assert!(validate_lines(&parse_hosts(ARCH_HOSTS)).is_ok());
+    }
+
+    #[test]
+    fn rejects_control_characters_in_disabled_entries() {
+        let mut line = entry("The", "following\n10.0.0.2 evil.local", None);
+        if let Line::Entry(entry) = &mut line {
+            entry.enabled = false;
+        }
+        assert!(validate_lines(&[line]).is_err());
+    }
+
+    #[test]
+    fn rejects_control_characters() {
+        assert!(validate_lines(&[entry("10.0.0.1", "app.local", Some("a\n10.0.0.2 evil.local"))]).is_err());
+        assert!(validate_lines(&[Line::Section("a\rb".to_string())]).is_err());
+        assert!(validate_lines(&[Line::Comment("# a\u{0}".to_string())]).is_err());
+    }
+
+    #[test]
+    fn rejects_comment_parsed_as_active_entry() {
+        let error = validate_lines(&[Line::Comment("10.0.0.1 evil.local".to_string())]).unwrap_err();
+        assert!(error.starts_with("Riga 1:"));
+        assert!(validate_lines(&[Line::Comment("# 10.0.0.1 disabled.local".to_string())]).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_ip_or_hostname() {
+        assert!(validate_lines(&[entry("10.0.0.300", "app.local", None)]).is_err());
+        assert!(validate_lines(&[entry("10.0.0.1", "bad host", None)]).is_err());
+        assert!(validate_lines(&[entry("10.0.0.1", "-app.local", None)]).is_err());
+    }
+
+    #[test]
+    fn accepts_ipv6_with_zone() {
+        assert!(validate_lines(&[entry("fe80::1%eth0", "router.local", None)]).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_zones() {
+        assert!(validate_lines(&[entry("10.0.0.1%\n6.6.6.6 bank.com", "a.local", None)]).is_err());
+        assert!(validate_lines(&[entry("10.0.0.1%eth0", "a.local", None)]).is_err());
+        assert!(validate_lines(&[entry("fe80::1%", "a.local", None)]).is_err());
+        assert!(validate_lines(&[entry("fe80::1%eth 0", "a.local", None)]).is_err());
+    }
+
+    #[test]
+    fn only_auth_failures_count_as_permission_denied() {
+        assert!(is_auth_failure("pkexec", Some(126), ""));
+        assert!(is_auth_failure("pkexec", Some(127), ""));
+        assert!(!is_auth_failure("pkexec", Some(1), "No space left on device"));
+        assert!(is_auth_failure("sudo", Some(1), "sudo: a password is required"));
+        assert!(!is_auth_failure("sudo", Some(1), "Riga 3: IP non valido"));
+        assert!(is_auth_failure("osascript", Some(1), "execution error: User canceled. (-128)"));
+        assert!(!is_auth_failure("osascript", Some(1), "execution error: Input non valido (1)"));
+    }
+
+    #[test]
+    fn reads_flush_outcomes_from_helper_output() {
+        let outcomes = flush_outcomes(br#"[{"cache":"nscd","result":{"Ok":null}}]"#);
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].cache, "nscd");
+        assert!(flush_outcomes(b"").is_empty());
+    }
+
+    #[test]
+    fn quotes_for_shell_and_applescript() {
+        assert_eq!(shell_quote("/Apps/it's"), "'/Apps/it'\\''s'");
+        assert_eq!(applescript_escape(r#"a "b" \c"#), r#"a \"b\" \\c"#);
+    }
+}
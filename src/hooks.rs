@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 30;
+
+// Comandi eseguiti prima e dopo la scrittura del file hosts, salvati nelle impostazioni
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookSettings {
+    #[serde(default)]
+    pub pre_apply: String,
+    #[serde(default)]
+    pub post_apply: String,
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_true")]
+    pub abort_on_pre_failure: bool,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_HOOK_TIMEOUT_SECS
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        HookSettings {
+            pre_apply: String::new(),
+            post_apply: String::new(),
+            timeout_secs: DEFAULT_HOOK_TIMEOUT_SECS,
+            abort_on_pre_failure: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    PreApply,
+    PostApply,
+}
+
+impl HookStage {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HookStage::PreApply => "pre-apply",
+            HookStage::PostApply => "post-apply",
+        }
+    }
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// Dati passati agli hook: nome del profilo e percorso come variabili d'ambiente,
+// il diff del file hosts sullo standard input
+pub struct HookContext<'a> {
+    pub profile_name: &'a str,
+    pub target_path: &'a Path,
+    pub diff: &'a str,
+}
+
+// Esecuzione di un hook, mostrata nel pannello dei log
+#[derive(Debug, Clone)]
+pub struct HookRun {
+    pub stage: HookStage,
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub elapsed_ms: u128,
+    pub output: String,
+}
+
+impl HookRun {
+    pub fn summary(&self) -> String {
+        let outcome = if self.timed_out {
+            "timeout".to_string()
+        } else {
+            match self.exit_code {
+                Some(code) => format!("exit {}", code),
+                None => "terminato da un segnale".to_string(),
+            }
+        };
+        format!("[{}] {} ({}, {} ms)", self.stage, self.command, outcome, self.elapsed_ms)
+    }
+}
+
+pub fn run_hook(command: &str, stage: HookStage, context: &HookContext, timeout: Duration) -> HookRun {
+    let start = Instant::now();
+    let failed = |output: String| HookRun {
+        stage,
+        command: command.to_string(),
+        success: false,
+        exit_code: None,
+        timed_out: false,
+        elapsed_ms: start.elapsed().as_millis(),
+        output,
+    };
+
+    let mut child = match shell(command)
+        .env("HOSTS_MANAGER_STAGE", stage.as_str())
+        .env("HOSTS_MANAGER_PROFILE", context.profile_name)
+        .env("HOSTS_MANAGER_HOSTS_PATH", context.target_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return failed(format!("Impossibile avviare il comando: {}", e)),
+    };
+
+    // Stdin e output vengono gestiti su thread separati: un hook che non legge
+    // lo stdin o scrive molto output non deve bloccare l'attesa del timeout
+    let stdin = child.stdin.take();
+    let diff = context.diff.to_string();
+    let writer = thread::spawn(move || {
+        if let Some(mut stdin) = stdin {
+            let _ = stdin.write_all(diff.as_bytes());
+        }
+    });
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let (status, timed_out) = wait_with_timeout(&mut child, timeout);
+    // Dopo un timeout eventuali processi figli potrebbero tenere aperto l'output:
+    // i thread di lettura vengono abbandonati invece di attenderli
+    let output = if timed_out {
+        format!("Interrotto dopo {} secondi.", timeout.as_secs())
+    } else {
+        let _ = writer.join();
+        let mut output = stdout.join().unwrap_or_default();
+        output.push_str(&stderr.join().unwrap_or_default());
+        output
+    };
+
+    HookRun {
+        stage,
+        command: command.to_string(),
+        success: !timed_out && status.is_some_and(|s| s.success()),
+        exit_code: status.and_then(|s| s.code()),
+        timed_out,
+        elapsed_ms: start.elapsed().as_millis(),
+        output,
+    }
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+fn read_in_background<R: Read + Send + 'static>(source: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = String::new();
+        if let Some(mut source) = source {
+            let _ = source.read_to_string(&mut buffer);
+        }
+        buffer
+    })
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> (Option<std::process::ExitStatus>, bool) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return (Some(status), false),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return (None, true);
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(_) => return (None, false),
+        }
+    }
+}
+
+// Diff semplificato tra il vecchio e il nuovo file hosts: righe rimosse con '-'
+// e righe aggiunte con '+', nell'ordine in cui compaiono
+pub fn hosts_diff(old: &str, new: &str) -> String {
+    let old_lines: HashSet<&str> = old.lines().collect();
+    let new_lines: HashSet<&str> = new.lines().collect();
+
+    let mut diff = String::new();
+    for line in old.lines().filter(|line| !new_lines.contains(line)) {
+        diff.push_str("- ");
+        diff.push_str(line);
+        diff.push('\n');
+    }
+    for line in new.lines().filter(|line| !old_lines.contains(line)) {
+        diff.push_str("+ ");
+        diff.push_str(line);
+        diff.push('\n');
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn context<'a>(diff: &'a str) -> HookContext<'a> {
+        HookContext { profile_name: "Lavoro", target_path: Path::new("/etc/hosts"), diff }
+    }
+
+    #[test]
+    fn diff_lists_removed_then_added_lines() {
+        let diff = hosts_diff("a\nb\nc\n", "a\nc\nd\n");
+        assert_eq!(diff, "- b\n+ d\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn passes_env_vars_and_diff_on_stdin() {
+        let run = run_hook(
+            r#"printf '%s|%s|%s|' "$HOSTS_MANAGER_STAGE" "$HOSTS_MANAGER_PROFILE" "$HOSTS_MANAGER_HOSTS_PATH"; cat"#,
+            HookStage::PreApply,
+            &context("+ 10.0.0.1 api.local\n"),
+            TIMEOUT,
+        );
+        assert!(run.success);
+        assert_eq!(run.exit_code, Some(0));
+        assert_eq!(run.output, "pre-apply|Lavoro|/etc/hosts|+ 10.0.0.1 api.local\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn captures_exit_code_and_stderr() {
+        let run = run_hook("echo errore >&2; exit 3", HookStage::PostApply, &context(""), TIMEOUT);
+        assert!(!run.success);
+        assert!(!run.timed_out);
+        assert_eq!(run.exit_code, Some(3));
+        assert_eq!(run.output, "errore\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_hooks_after_the_timeout() {
+        let run = run_hook("sleep 30", HookStage::PreApply, &context(""), Duration::from_millis(200));
+        assert!(run.timed_out);
+        assert!(!run.success);
+        assert_eq!(run.exit_code, None);
+        assert!(run.elapsed_ms < 10_000);
+    }
+}
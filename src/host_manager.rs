@@ -1,7 +1,10 @@
 use std::fs;
 use serde::{Deserialize, Serialize};
 use std::env;
-use std::path::PathBuf;
+use std::borrow::Borrow;
+use std::fmt::{self, Write as _};
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::{Path, PathBuf};
 
 
 #[derive(Debug, Clone, PartialEq, Eq,Serialize, Deserialize)]
@@ -22,6 +25,18 @@ pub enum Line {
     Entry(Entry),
     Comment(String),
     Empty,
+    // Intestazione di una sezione: raggruppa i record che la seguono fino alla sezione successiva
+    Section(String),
+}
+
+// Forma di una sezione nel file hosts, riconosciuta di nuovo in lettura
+const SECTION_PREFIX: &str = "# === ";
+const SECTION_SUFFIX: &str = " ===";
+const MAX_HOSTNAME_LEN: usize = 253;
+
+fn parse_section(line: &str) -> Option<&str> {
+    let name = line.strip_prefix(SECTION_PREFIX)?.strip_suffix(SECTION_SUFFIX)?.trim();
+    (!name.is_empty()).then_some(name)
 }
 
 
@@ -32,11 +47,23 @@ pub fn load_hosts_entries() -> Vec<Line> {
         println!("Error reading host file: {}", e);
         String::new()
     });
+    parse_hosts(&contents)
+}
+
+// Legge un qualsiasi file in formato hosts (es. uno snippet condiviso da un collega)
+pub fn load_hosts_file(path: &Path) -> std::io::Result<Vec<Line>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(parse_hosts(&contents))
+}
+
+pub fn parse_hosts(contents: &str) -> Vec<Line> {
     contents.lines()
         .map(|line| {
             let trimmed_line = line.trim();
             if trimmed_line.is_empty() {
                 Line::Empty
+            } else if let Some(name) = parse_section(trimmed_line) {
+                Line::Section(name.to_string())
             } else {
                 let mut is_enabled = true;
                 let mut current_parse_line = trimmed_line;
@@ -71,9 +98,127 @@ pub fn load_hosts_entries() -> Vec<Line> {
         .collect()
 }
 
-pub fn write_hosts_entries_to_file(entries: &[Line]) -> std::io::Result<()> {
+// Come risolvere un hostname presente in entrambi i profili con IP diversi
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeResolution {
+    #[default]
+    KeepOurs,
+    TakeTheirs,
+    KeepBothDisabled,
+}
+
+impl MergeResolution {
+    pub const ALL: [MergeResolution; 3] = [
+        MergeResolution::KeepOurs,
+        MergeResolution::TakeTheirs,
+        MergeResolution::KeepBothDisabled,
+    ];
+}
+
+impl fmt::Display for MergeResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeResolution::KeepOurs => write!(f, "Mantieni il nostro"),
+            MergeResolution::TakeTheirs => write!(f, "Usa l'importato"),
+            MergeResolution::KeepBothDisabled => write!(f, "Entrambi disabilitati"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeConflict {
+    pub hostname: String,
+    pub ours: Entry,
+    pub theirs: Entry,
+    pub resolution: MergeResolution,
+}
+
+fn entries_of(lines: &[Line]) -> impl Iterator<Item = &Entry> {
+    lines.iter().filter_map(|line| match line {
+        Line::Entry(entry) => Some(entry),
+        _ => None,
+    })
+}
+
+// Hostname presenti in entrambi i profili ma mai con lo stesso IP, uno per hostname
+pub fn find_merge_conflicts(existing: &[Line], incoming: &[Line]) -> Vec<MergeConflict> {
+    let mut conflicts: Vec<MergeConflict> = Vec::new();
+    for theirs in entries_of(incoming) {
+        if conflicts.iter().any(|c| c.hostname == theirs.hostname) {
+            continue;
+        }
+        let same_mapping = entries_of(existing).any(|e| e.hostname == theirs.hostname && e.ip == theirs.ip);
+        if same_mapping {
+            continue;
+        }
+        if let Some(ours) = entries_of(existing).find(|e| e.hostname == theirs.hostname) {
+            conflicts.push(MergeConflict {
+                hostname: theirs.hostname.clone(),
+                ours: ours.clone(),
+                theirs: theirs.clone(),
+                resolution: MergeResolution::default(),
+            });
+        }
+    }
+    conflicts
+}
+
+// Record di `incoming` con hostname non presente in `existing`, da accodare in un merge
+pub fn new_entries_for_merge(existing: &[Line], incoming: &[Line]) -> Vec<Line> {
+    let mut added: Vec<Line> = Vec::new();
+    for line in incoming {
+        if let Line::Entry(entry) = line {
+            let already_present = entries_of(existing).any(|e| e.hostname == entry.hostname)
+                || entries_of(&added).any(|e| e.ip == entry.ip && e.hostname == entry.hostname);
+            if !already_present {
+                added.push(line.clone());
+            }
+        }
+    }
+    added
+}
+
+// Applica le scelte sui conflitti alle righe esistenti e accoda i record nuovi
+pub fn merge_lines(existing: &[Line], incoming: &[Line], conflicts: &[MergeConflict]) -> Vec<Line> {
+    let mut merged: Vec<Line> = Vec::with_capacity(existing.len());
+
+    for line in existing {
+        let conflict = match line {
+            Line::Entry(entry) => conflicts.iter().find(|c| c.hostname == entry.hostname),
+            _ => None,
+        };
+        match (line, conflict) {
+            (Line::Entry(_), Some(c)) if c.resolution == MergeResolution::TakeTheirs => {
+                // Il primo record dell'hostname viene sostituito, gli altri eliminati
+                if !merged.iter().any(|l| matches!(l, Line::Entry(e) if e.hostname == c.hostname)) {
+                    merged.push(Line::Entry(c.theirs.clone()));
+                }
+            }
+            (Line::Entry(entry), Some(c)) if c.resolution == MergeResolution::KeepBothDisabled => {
+                merged.push(Line::Entry(Entry { enabled: false, ..entry.clone() }));
+            }
+            _ => merged.push(line.clone()),
+        }
+    }
+
+    for conflict in conflicts {
+        if conflict.resolution == MergeResolution::KeepBothDisabled {
+            merged.push(Line::Entry(Entry { enabled: false, ..conflict.theirs.clone() }));
+        }
+    }
+
+    merged.extend(new_entries_for_merge(existing, incoming));
+    merged
+}
+
+pub fn write_hosts_entries_to_path<L: Borrow<Line>>(path: &Path, entries: &[L]) -> std::io::Result<()> {
+    fs::write(path, render_hosts_file(entries).as_bytes())
+}
+
+// Contenuto completo del file hosts, con il record localhost sempre in testa
+pub fn render_hosts_file<L: Borrow<Line>>(entries: &[L]) -> String {
     // Aggiungi sempre il record localhost
-    let mut updated_entries = vec![
+    let header = [
         Line::Comment("".to_string()), // Aggiungi una riga vuota per chiarezza
         Line::Entry(Entry {
             ip: "127.0.0.1".to_string(),
@@ -83,48 +228,71 @@ pub fn write_hosts_entries_to_file(entries: &[Line]) -> std::io::Result<()> {
         }),
     ];
 
-    for line in entries {
-        // Ignora il record localhost nel file originale per evitare duplicati
-        if let Line::Entry(entry) = line {
-            if entry.ip == "127.0.0.1" && entry.hostname == "localhost" {
-                continue;
-            }
-        }
-        updated_entries.push(line.clone());
-    }
+    // Ignora il record localhost nel file originale per evitare duplicati.
+    // Le righe non vengono copiate: con profili da 100k record la copia pesa più della scrittura.
+    let filtered = entries.iter().map(|line| line.borrow()).filter(|line| {
+        !matches!(line, Line::Entry(entry) if entry.ip == "127.0.0.1" && entry.hostname == "localhost")
+    });
 
-    let content: String = updated_entries
-        .iter()
-        .map(|line| match line {
+    render_hosts(header.iter().chain(filtered))
+}
+
+// Formatta le righe nel formato del file hosts, senza aggiungere il record localhost
+pub fn render_hosts<'a>(lines: impl IntoIterator<Item = &'a Line>) -> String {
+    let mut content = String::new();
+    for line in lines {
+        match line {
             Line::Entry(entry) => {
-                let mut s = String::new();
                 if !entry.enabled {
-                    s.push_str("# "); // Prepend '#' if disabled
+                    content.push_str("# "); // Prepend '#' if disabled
                 }
 
                 // Formatta IP e Hostname
-                s.push_str(&format!("{:<15} {}", entry.ip, entry.hostname));
+                let _ = write!(content, "{:<15} {}", entry.ip, entry.hostname);
 
                 // Aggiungi il commento a fine riga se presente
                 if let Some(comment_text) = &entry.comment {
-                    s.push_str(" # "); // Add " #" before the comment
-                    s.push_str(comment_text);
+                    content.push_str(" # "); // Add " #" before the comment
+                    content.push_str(comment_text);
                 }
-                s.push_str("\n");
-                s
+                content.push('\n');
             },
-            Line::Comment(comment) => format!("{}\n", comment),
-            Line::Empty => "\n".to_string(),
-        })
-        .collect();
+            Line::Comment(comment) => {
+                content.push_str(comment);
+                content.push('\n');
+            },
+            Line::Empty => content.push('\n'),
+            Line::Section(name) => {
+                let _ = writeln!(content, "{}{}{}", SECTION_PREFIX, name, SECTION_SUFFIX);
+            },
+        }
+    }
+    content
+}
 
-    fs::write(get_hosts_file_path(), content.as_bytes())?;
+// Hostname accettato nel file hosts: lettere, cifre, '-', '.' e '_', senza essere un indirizzo IP
+pub fn is_valid_hostname(hostname: &str) -> bool {
+    !hostname.is_empty()
+        && hostname.len() <= MAX_HOSTNAME_LEN
+        && !hostname.starts_with(['-', '.'])
+        && hostname.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))
+        && hostname.parse::<IpAddr>().is_err()
+}
 
-    Ok(())
+// Gli indirizzi link-local IPv6 possono avere l'interfaccia dopo '%': solo lettere, cifre, '_', '.' e '-'
+pub fn is_valid_ip(ip: &str) -> bool {
+    match ip.split_once('%') {
+        Some((address, zone)) => {
+            address.parse::<Ipv6Addr>().is_ok()
+                && !zone.is_empty()
+                && zone.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        }
+        None => ip.parse::<IpAddr>().is_ok(),
+    }
 }
 
 // Ritorna il percorso corretto del file hosts in base al sistema operativo
-fn get_hosts_file_path() -> PathBuf {
+pub fn get_hosts_file_path() -> PathBuf {
     let os = env::consts::OS;
 
     match os {
@@ -140,4 +308,86 @@ fn get_hosts_file_path() -> PathBuf {
         // Linux, macOS e altri sistemi Unix-like usano /etc/hosts
         _ => PathBuf::from("/etc/hosts"),
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ip: &str, hostname: &str) -> Entry {
+        Entry { ip: ip.to_string(), hostname: hostname.to_string(), enabled: true, comment: None }
+    }
+
+    fn lines(pairs: &[(&str, &str)]) -> Vec<Line> {
+        pairs.iter().map(|(ip, hostname)| Line::Entry(entry(ip, hostname))).collect()
+    }
+
+    fn resolved(existing: &[Line], incoming: &[Line], resolution: MergeResolution) -> Vec<Line> {
+        let mut conflicts = find_merge_conflicts(existing, incoming);
+        for conflict in &mut conflicts {
+            conflict.resolution = resolution;
+        }
+        merge_lines(existing, incoming, &conflicts)
+    }
+
+    #[test]
+    fn conflicts_only_for_hostnames_never_mapped_to_the_same_ip() {
+        let existing = lines(&[("10.0.0.1", "api.local"), ("10.0.0.2", "db.local"), ("10.0.0.9", "db.local")]);
+        let incoming = lines(&[("10.0.0.5", "api.local"), ("10.0.0.6", "api.local"), ("10.0.0.9", "db.local")]);
+        let conflicts = find_merge_conflicts(&existing, &incoming);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].hostname, "api.local");
+        assert_eq!(conflicts[0].ours.ip, "10.0.0.1");
+        assert_eq!(conflicts[0].theirs.ip, "10.0.0.5");
+    }
+
+    #[test]
+    fn keep_ours_leaves_existing_entries() {
+        let existing = lines(&[("10.0.0.1", "api.local")]);
+        let incoming = lines(&[("10.0.0.5", "api.local")]);
+        assert_eq!(resolved(&existing, &incoming, MergeResolution::KeepOurs), existing);
+    }
+
+    #[test]
+    fn take_theirs_replaces_only_the_first_match() {
+        let existing = vec![
+            Line::Entry(entry("10.0.0.1", "api.local")),
+            Line::Comment("# nota".to_string()),
+            Line::Entry(entry("10.0.0.2", "api.local")),
+        ];
+        let incoming = lines(&[("10.0.0.5", "api.local")]);
+        assert_eq!(
+            resolved(&existing, &incoming, MergeResolution::TakeTheirs),
+            vec![Line::Entry(entry("10.0.0.5", "api.local")), Line::Comment("# nota".to_string())]
+        );
+    }
+
+    #[test]
+    fn keep_both_disabled_keeps_both_entries_disabled() {
+        let existing = lines(&[("10.0.0.1", "api.local")]);
+        let incoming = lines(&[("10.0.0.5", "api.local")]);
+        let disabled = |ip: &str| Line::Entry(Entry { enabled: false, ..entry(ip, "api.local") });
+        assert_eq!(
+            resolved(&existing, &incoming, MergeResolution::KeepBothDisabled),
+            vec![disabled("10.0.0.1"), disabled("10.0.0.5")]
+        );
+    }
+
+    #[test]
+    fn new_entries_are_deduplicated() {
+        let existing = lines(&[("10.0.0.1", "api.local")]);
+        let incoming = lines(&[
+            ("10.0.0.3", "web.local"),
+            ("10.0.0.3", "web.local"),
+            ("10.0.0.4", "web.local"),
+            ("10.0.0.5", "api.local"),
+        ]);
+        assert_eq!(
+            new_entries_for_merge(&existing, &incoming),
+            lines(&[("10.0.0.3", "web.local"), ("10.0.0.4", "web.local")])
+        );
+        assert_eq!(
+            resolved(&existing, &incoming, MergeResolution::KeepOurs),
+            lines(&[("10.0.0.1", "api.local"), ("10.0.0.3", "web.local"), ("10.0.0.4", "web.local")])
+        );
+    }
+}
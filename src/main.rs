@@ -3,9 +3,37 @@ mod app;
 mod db_manager;
 mod profile_view;
 mod dns_lookup;
+mod bundle;
+mod drift_report;
+mod drift_view;
+mod exporters;
+mod health_check;
+mod sources;
+mod benchmark;
+mod entry_filter;
+mod validation;
+mod dns_cache;
+mod hooks;
+mod apply;
+mod helper;
 
 use app::init_app;
 
 pub fn main() -> iced::Result {
+    // Modalità helper: scrive il file hosts con i privilegi ottenuti da pkexec/sudo, senza GUI
+    if std::env::args().any(|arg| arg == helper::HELPER_FLAG) {
+        if let Err(e) = helper::run() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if std::env::args().any(|arg| arg == "--benchmark") {
+        if let Err(e) = benchmark::run() {
+            eprintln!("Benchmark fallito: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     init_app()
 }
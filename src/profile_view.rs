@@ -1,10 +1,14 @@
 
-use iced::{Alignment, Color, Element, Length, widget::{column, text, button, text_input, row, scrollable, container, Space}, Theme, Renderer};
+use iced::{Alignment, Color, Element, Length, widget::{column, text, button, checkbox, text_input, row, scrollable, container, pick_list, Space}, Theme, Renderer};
 use iced::widget::{Row,Text};
-use crate::app::{Message, MyApp};
+use crate::app::{ConflictChoice, ImportTarget, Message, MyApp};
+use crate::db_manager::{db_location, ImportAction};
+use crate::host_manager::{Line, MergeResolution};
+use crate::exporters::ExportFormat;
+use crate::health_check::summarize;
 
 
-pub fn view(state: &MyApp) -> Element<Message> {
+pub fn view(state: &MyApp) -> Element<'_, Message> {
 
     let status_label: Text<'_, Theme, Renderer> = if let Some(msg) = &state.error_message {
         text(msg).size(16).color(Color::from_rgb(0.8, 0.2, 0.2)) // Rosso per gli errori
@@ -25,20 +29,33 @@ pub fn view(state: &MyApp) -> Element<Message> {
     let profiles_list: Vec<Element<Message>> = state.profiles
         .iter()
         .map(|profile| {
-            let select_button = if state.selected_profile.as_ref().map(|p| p.id.clone()) == Some(profile.id.clone()) {
+            let select_button = if state.selected_profile.as_ref().is_some_and(|p| p.id == profile.id) {
                 button("Attivo")
             } else {
-                button("Seleziona").on_press(Message::ProfileSelected(profile.clone())).into()
+                button("Seleziona").on_press(Message::ProfileSelected(profile.id.clone()))
+            };
+
+            let edit_button = if state.editing_profile_id.as_ref() == Some(&profile.id) {
+                button("In modifica")
+            } else {
+                button("Modifica").on_press(Message::EditProfile(profile.id.clone()))
             };
 
             let delete_button = if profile.name == "Default" {
                 button("Default")
             } else {
-                button("Elimina").on_press(Message::DeleteProfile(profile.id.clone())).into()
+                button("Elimina").on_press(Message::DeleteProfile(profile.id.clone()))
+            };
+
+            let health_summary = match summarize(&profile.hosts, &state.health_results) {
+                Some((reachable, checked)) => format!("{}/{} raggiungibili", reachable, checked),
+                None => String::new(),
             };
 
             row![
                 text(&profile.name).width(Length::Fill),
+                text(health_summary).size(14),
+                edit_button,
                 select_button,
                 delete_button,
             ]
@@ -51,22 +68,254 @@ pub fn view(state: &MyApp) -> Element<Message> {
     let scrollable_profiles = scrollable(column(profiles_list).spacing(5)).height(Length::FillPortion(1));
 
     let import_export_row: Row<'_, _, Theme, Renderer> = row![
+        pick_list(ExportFormat::ALL, Some(state.export_format), Message::ExportFormatSelected),
         button("Esporta profilo selezionato").on_press(Message::ExportProfilesButtonPressed),
         button("Importa profilo").on_press(Message::ImportProfilesButtonPressed),
+        button("Importa file hosts").on_press(Message::ImportHostsFileButtonPressed),
     ]
         .spacing(10)
         .align_y(Alignment::Center);
 
+    let bundle_row: Row<'_, _, Theme, Renderer> = row![
+        button("Esporta tutti i profili").on_press(Message::ExportBundleButtonPressed),
+        button("Importa bundle").on_press(Message::ImportBundleButtonPressed),
+    ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    let import_preview: Element<Message> = match &state.hosts_import {
+        Some(import) => {
+            let mut targets = vec![ImportTarget::NewProfile];
+            targets.extend(state.profiles.iter().map(|p| ImportTarget::Existing {
+                id: p.id.clone(),
+                name: p.name.clone(),
+            }));
+
+            let lines_to_add = import.lines_to_add(state);
+            let entries_count = lines_to_add.iter().filter(|l| matches!(l, Line::Entry(_))).count();
+            let preview_rows: Vec<Element<Message>> = lines_to_add
+                .iter()
+                .filter_map(|line| match line {
+                    Line::Entry(entry) => Some(text(format!("{:<15} {}", entry.ip, entry.hostname)).size(14).into()),
+                    _ => None,
+                })
+                .collect();
+
+            let name_input: Element<Message> = if import.target == ImportTarget::NewProfile {
+                text_input("Nome del profilo", &import.profile_name)
+                    .on_input(Message::HostsImportNameChanged)
+                    .width(Length::Fill)
+                    .into()
+            } else {
+                Space::with_width(Length::Fill).into()
+            };
+
+            let conflict_rows: Vec<Element<Message>> = import.conflicts
+                .iter()
+                .enumerate()
+                .map(|(index, conflict)| {
+                    row![
+                        text(format!("{}: {} -> {}", conflict.hostname, conflict.ours.ip, conflict.theirs.ip))
+                            .size(14)
+                            .width(Length::Fill),
+                        pick_list(MergeResolution::ALL, Some(conflict.resolution), move |r| Message::MergeResolutionSelected(index, r)),
+                    ]
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .into()
+                })
+                .collect();
+
+            container(
+                column![
+                    text(format!("Anteprima import: {} record da aggiungere, {} conflitti", entries_count, import.conflicts.len())).size(18),
+                    row![
+                        pick_list(targets, Some(import.target.clone()), Message::HostsImportTargetSelected),
+                        name_input,
+                        button("Importa").on_press(Message::ConfirmHostsImport),
+                        button("Annulla").on_press(Message::CancelHostsImport),
+                    ]
+                        .spacing(10)
+                        .align_y(Alignment::Center),
+                    scrollable(column(conflict_rows).spacing(5)).height(Length::Shrink),
+                    scrollable(column(preview_rows).spacing(2)).height(Length::Fixed(150.0)),
+                ]
+                    .spacing(10)
+            )
+                .padding(15)
+                .style(container::rounded_box)
+                .into()
+        }
+        None => Space::with_height(0).into(),
+    };
+
+    let bundle_preview: Element<Message> = match &state.bundle_import {
+        Some(import) => {
+            let rows: Vec<Element<Message>> = import.bundle.profiles
+                .iter()
+                .zip(import.actions.iter())
+                .enumerate()
+                .map(|(index, (profile, action))| {
+                    let conflict = state.profiles.iter().any(|p| p.name == profile.name);
+                    let choice: Element<Message> = if !conflict {
+                        text("Nuovo").size(14).into()
+                    } else {
+                        let selected = match action {
+                            ImportAction::Overwrite => ConflictChoice::Overwrite,
+                            ImportAction::Rename(_) => ConflictChoice::Rename,
+                            _ => ConflictChoice::Skip,
+                        };
+                        let mut choice_row = row![
+                            pick_list(ConflictChoice::ALL, Some(selected), move |c| Message::BundleConflictChoiceSelected(index, c)),
+                        ]
+                            .spacing(10)
+                            .align_y(Alignment::Center);
+                        if let ImportAction::Rename(name) = action {
+                            choice_row = choice_row.push(
+                                text_input("Nuovo nome", name)
+                                    .on_input(move |n| Message::BundleRenameChanged(index, n))
+                                    .width(Length::Fixed(200.0)));
+                        }
+                        choice_row.into()
+                    };
+
+                    row![
+                        text(format!("{} ({} righe)", profile.name, profile.hosts.len())).width(Length::Fill),
+                        choice,
+                    ]
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .into()
+                })
+                .collect();
+
+            container(
+                column![
+                    text(format!("Bundle v{} con {} profili", import.bundle.app_version, import.bundle.profiles.len())).size(18),
+                    scrollable(column(rows).spacing(5)).height(Length::Fixed(150.0)),
+                    checkbox(format!("Importa impostazioni ({})", import.bundle.settings.len()), import.import_settings)
+                        .on_toggle(Message::BundleImportSettingsToggled),
+                    row![
+                        button("Importa").on_press(Message::ConfirmBundleImport),
+                        button("Annulla").on_press(Message::CancelBundleImport),
+                    ]
+                        .spacing(10),
+                ]
+                    .spacing(10)
+            )
+                .padding(15)
+                .style(container::rounded_box)
+                .into()
+        }
+        None => Space::with_height(0).into(),
+    };
+
+    let search_row = row![
+        text_input("Cerca in tutti i profili (hostname, IP o commento)", &state.global_search_query)
+            .on_input(Message::GlobalSearchChanged)
+            .on_submit(Message::GlobalSearchButtonPressed)
+            .width(Length::Fill),
+        button("Cerca").on_press(Message::GlobalSearchButtonPressed),
+    ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    let search_results: Vec<Element<Message>> = state.global_search_results
+        .iter()
+        .map(|found| {
+            let entry = &found.entry;
+            let state_label = if entry.enabled { "" } else { " (disabilitato)" };
+            row![
+                text(&found.profile_name).width(Length::FillPortion(1)),
+                text(format!("riga {}", found.line_index + 1)).size(14),
+                text(format!("{:<15} {}{}", entry.ip, entry.hostname, state_label)).width(Length::FillPortion(2)),
+                button(text(if found.is_active { "Vai (attivo)" } else { "Vai" }).size(14))
+                    .on_press(Message::JumpToEntry(found.profile_id.clone(), found.line_index)),
+            ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+                .into()
+        })
+        .collect();
+
+    let scrollable_search_results: Element<Message> = if search_results.is_empty() {
+        Space::with_height(0).into()
+    } else {
+        scrollable(column(search_results).spacing(5)).height(Length::FillPortion(1)).into()
+    };
+
+    let hooks_section = column![
+        text("Hook di applicazione:").size(20),
+        text_input("Comando pre-apply (es. script di backup)", &state.hook_draft.pre_apply)
+            .on_input(Message::PreApplyHookChanged),
+        text_input("Comando post-apply (es. riavvio di un servizio)", &state.hook_draft.post_apply)
+            .on_input(Message::PostApplyHookChanged),
+        row![
+            text("Timeout (secondi):"),
+            text_input("30", &state.hook_timeout_input)
+                .on_input(Message::HookTimeoutChanged)
+                .width(Length::Fixed(80.0)),
+            checkbox("Annulla l'applicazione se il pre-apply fallisce", state.hook_draft.abort_on_pre_failure)
+                .on_toggle(Message::AbortOnPreHookFailureToggled),
+            button("Salva hook").on_press(Message::SaveHookSettings),
+        ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+    ]
+        .spacing(10);
+
+    let hook_log: Element<Message> = if state.hook_log.is_empty() {
+        Space::with_height(0).into()
+    } else {
+        let runs: Vec<Element<Message>> = state.hook_log
+            .iter()
+            .rev()
+            .map(|run| {
+                let color = if run.success { Color::from_rgb(0.0, 0.6, 0.0) } else { Color::from_rgb(0.8, 0.0, 0.0) };
+                column![
+                    text(run.summary()).size(14).color(color),
+                    text(run.output.trim_end()).size(12),
+                ]
+                    .spacing(2)
+                    .into()
+            })
+            .collect();
+        column![
+            row![
+                text("Log degli hook:").width(Length::Fill),
+                button(text("Svuota log").size(14)).on_press(Message::ClearHookLog),
+            ]
+                .align_y(Alignment::Center),
+            scrollable(column(runs).spacing(8)).height(Length::Fixed(150.0)),
+        ]
+            .spacing(5)
+            .into()
+    };
+
+    // L'avviso compare se l'utente che ha lanciato sudo o pkexec non è stato trovato
+    let database_info = column![
+        text(format!("Database: {} ({})", db_location().path.display(), db_location().origin)).size(14),
+    ]
+        .push_maybe(db_location().warning.as_ref().map(|warning| text(warning).size(14).color(Color::from_rgb(0.8, 0.2, 0.2))));
+
     let content = column![
         text("Gestione dei Profili").size(30).color(Color::from_rgb(0.1, 0.5, 0.8)),
+        database_info,
         Space::with_height(20),
         new_profile_input,
         Space::with_height(20),
         status_label,
         text("Profili esistenti:").size(20),
         scrollable_profiles,
+        search_row,
+        scrollable_search_results,
         Space::with_height(20),
         import_export_row,
+        bundle_row,
+        import_preview,
+        bundle_preview,
+        hooks_section,
+        hook_log,
         Space::with_height(20),
         button("Torna alla vista principale").on_press(Message::ShowMainView) 
     ]
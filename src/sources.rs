@@ -0,0 +1,262 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::host_manager::{is_valid_hostname, is_valid_ip, parse_hosts, Entry, Line};
+
+// Formato di un file sorgente sottoscritto da un profilo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceFormat {
+    #[default]
+    Hosts,
+    DomainList,
+}
+
+impl SourceFormat {
+    pub const ALL: [SourceFormat; 2] = [SourceFormat::Hosts, SourceFormat::DomainList];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceFormat::Hosts => "hosts",
+            SourceFormat::DomainList => "domains",
+        }
+    }
+
+    pub fn from_db(value: &str) -> SourceFormat {
+        match value {
+            "domains" => SourceFormat::DomainList,
+            _ => SourceFormat::Hosts,
+        }
+    }
+}
+
+impl fmt::Display for SourceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceFormat::Hosts => write!(f, "Formato hosts"),
+            SourceFormat::DomainList => write!(f, "Un dominio per riga"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileSource {
+    pub id: String,
+    pub profile_id: String,
+    pub path: String,
+    pub format: SourceFormat,
+}
+
+// Data di modifica e dimensione: se non cambiano il file non viene riletto
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceFingerprint {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+}
+
+// Record letti da una sorgente. Sono in sola lettura e condivisi tramite Arc,
+// così le liste da 100k righe non vengono copiate a ogni messaggio o salvataggio.
+#[derive(Debug, Clone)]
+pub struct SourceData {
+    pub fingerprint: Option<SourceFingerprint>,
+    pub lines: Arc<Vec<Line>>,
+    pub error: Option<String>,
+    // Righe scartate perché non sono record validi per il file hosts
+    pub skipped: usize,
+}
+
+pub fn fingerprint(path: &Path) -> io::Result<SourceFingerprint> {
+    let metadata = fs::metadata(path)?;
+    Ok(SourceFingerprint {
+        modified: metadata.modified().ok(),
+        len: metadata.len(),
+    })
+}
+
+pub fn load_source(source: &ProfileSource) -> SourceData {
+    let path = Path::new(&source.path);
+    let result = fingerprint(path).and_then(|fp| {
+        let contents = fs::read_to_string(path)?;
+        let (lines, skipped) = match source.format {
+            SourceFormat::Hosts => parse_hosts_source(&contents),
+            SourceFormat::DomainList => parse_domain_list(&contents),
+        };
+        Ok((fp, lines, skipped))
+    });
+
+    match result {
+        Ok((fp, lines, skipped)) => SourceData {
+            fingerprint: Some(fp),
+            lines: Arc::new(lines),
+            error: None,
+            skipped,
+        },
+        Err(e) => SourceData {
+            fingerprint: None,
+            lines: Arc::new(Vec::new()),
+            error: Some(format!("{}: {}", source.path, e)),
+            skipped: 0,
+        },
+    }
+}
+
+// Aggiorna la cache con una rilettura. Se la lettura fallisce (es. una condivisione di rete
+// offline) restano righe e impronta precedenti e cambia solo l'errore.
+// Ritorna true se le righe sono cambiate e il file hosts va riscritto: la prima lettura
+// non conta come modifica, altrimenti ogni avvio riscriverebbe il file hosts.
+pub fn update_cache(cache: &mut HashMap<String, SourceData>, id: String, fresh: SourceData) -> bool {
+    if fresh.error.is_some() {
+        match cache.get_mut(&id) {
+            Some(cached) => cached.error = fresh.error,
+            None => {
+                cache.insert(id, fresh);
+            }
+        }
+        return false;
+    }
+    let changed = cache.get(&id).is_some_and(|cached| cached.lines != fresh.lines);
+    cache.insert(id, fresh);
+    changed
+}
+
+// Sorgente del profilo attivo, con le righe in cache se è già stata letta
+pub type PendingSource = (ProfileSource, Option<Arc<Vec<Line>>>);
+
+// Righe delle sorgenti da scrivere nel file hosts. Quelle non ancora in cache (es. subito dopo
+// l'avvio) vengono lette qui, dentro il task: una scrittura fatta prima del primo aggiornamento
+// altrimenti le toglierebbe dal file
+pub fn resolve_sources(sources: &[PendingSource]) -> Vec<(String, Arc<Vec<Line>>)> {
+    sources
+        .iter()
+        .map(|(source, cached)| {
+            let lines = cached.clone().unwrap_or_else(|| load_source(source).lines);
+            (source.path.clone(), lines)
+        })
+        .collect()
+}
+
+// Solo i record abilitati e validi di un file in formato hosts
+fn parse_hosts_source(contents: &str) -> (Vec<Line>, usize) {
+    let mut skipped = 0;
+    let lines = parse_hosts(contents)
+        .into_iter()
+        .filter(|line| match line {
+            Line::Entry(entry) if entry.enabled => {
+                let valid = is_valid_ip(&entry.ip) && is_valid_hostname(&entry.hostname);
+                skipped += usize::from(!valid);
+                valid
+            }
+            _ => false,
+        })
+        .collect();
+    (lines, skipped)
+}
+
+// Blocklist con un dominio per riga: ogni dominio viene puntato a 0.0.0.0.
+// Sono ignorati i commenti ('#' e '!' delle liste adblock) e le righe vuote; delle regole
+// adblock (`||ads.com^`) e delle righe in formato hosts (`0.0.0.0 ads.com`) si prende il dominio.
+// Ritorna anche il numero di righe scartate perché il dominio non è un hostname valido.
+pub fn parse_domain_list(contents: &str) -> (Vec<Line>, usize) {
+    let mut skipped = 0;
+    let lines = contents
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line.split(['#', '!']).next().unwrap_or("").split_whitespace();
+            let first = tokens.next()?;
+            let token = match tokens.next() {
+                Some(second) if is_valid_ip(first) => second,
+                _ => first,
+            };
+            let domain = token.strip_prefix("||").unwrap_or(token).split('^').next().unwrap_or("");
+            if !is_valid_hostname(domain) {
+                skipped += 1;
+                return None;
+            }
+            Some(Line::Entry(Entry {
+                ip: "0.0.0.0".to_string(),
+                hostname: domain.to_string(),
+                enabled: true,
+                comment: None,
+            }))
+        })
+        .collect();
+    (lines, skipped)
+}
+
+// Righe da scrivere nel file hosts: prima quelle del profilo, poi quelle delle sorgenti
+// sotto un commento di intestazione. Gli hostname già definiti dal profilo hanno la precedenza.
+// Le righe esistenti vengono solo prese in prestito, senza copiarle.
+pub fn lines_with_sources<'a>(own: &'a [Line], sources: &'a [(String, Arc<Vec<Line>>)]) -> Vec<Cow<'a, Line>> {
+    let mut seen: HashSet<&str> = own
+        .iter()
+        .filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry.hostname.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let total: usize = own.len() + sources.iter().map(|(_, lines)| lines.len() + 2).sum::<usize>();
+    let mut merged = Vec::with_capacity(total);
+    merged.extend(own.iter().map(Cow::Borrowed));
+
+    for (path, lines) in sources {
+        merged.push(Cow::Owned(Line::Empty));
+        merged.push(Cow::Owned(Line::Comment(format!("# --- source: {} (read-only) ---", path))));
+        for line in lines.iter() {
+            if let Line::Entry(entry) = line
+                && seen.insert(entry.hostname.as_str()) {
+                merged.push(Cow::Borrowed(line));
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hostnames(lines: &[Line]) -> Vec<&str> {
+        lines.iter().filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry.hostname.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn domain_list_accepts_plain_adblock_and_hosts_lines() {
+        let (lines, skipped) = parse_domain_list("ads.com\n||tracker.net^\n||cdn.ads.org^$third-party\n0.0.0.0 hosts-style.com\n! commento\n# commento\n\n");
+        assert_eq!(hostnames(&lines), ["ads.com", "tracker.net", "cdn.ads.org", "hosts-style.com"]);
+        assert_eq!(skipped, 0);
+    }
+
+    fn data(hostnames: &[&str]) -> SourceData {
+        let lines = hostnames.iter().map(|hostname| Line::Entry(Entry {
+            ip: "0.0.0.0".to_string(),
+            hostname: hostname.to_string(),
+            enabled: true,
+            comment: None,
+        })).collect();
+        SourceData { fingerprint: None, lines: Arc::new(lines), error: None, skipped: 0 }
+    }
+
+    #[test]
+    fn first_cache_fill_is_not_a_change() {
+        let mut cache = HashMap::new();
+        assert!(!update_cache(&mut cache, "s".to_string(), data(&["a.com"])));
+        assert!(!update_cache(&mut cache, "s".to_string(), data(&["a.com"])));
+        assert!(update_cache(&mut cache, "s".to_string(), data(&["a.com", "b.com"])));
+    }
+
+    #[test]
+    fn domain_list_skips_invalid_hostnames() {
+        let (lines, skipped) = parse_domain_list("0.0.0.0\n/ads/*\nok.com\n0.0.0.0 0.0.0.0\n");
+        assert_eq!(hostnames(&lines), ["ok.com"]);
+        assert_eq!(skipped, 3);
+    }
+}
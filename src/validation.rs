@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use crate::host_manager::{Entry, Line};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    // Stesso IP e hostname di una riga precedente: la riga è inutile
+    Duplicate,
+    // Prima riga di un hostname mappato anche su altri IP: è quella che il sistema usa
+    Conflict,
+    // Hostname già definito più sopra con un altro IP: il sistema non la userà mai
+    Shadowed,
+    // Ridefinisce "localhost", che l'applicazione scrive sempre come 127.0.0.1 in testa al file
+    LocalhostCollision,
+}
+
+// Avviso su una riga; `other_line` è l'indice della riga con cui è in conflitto
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub other_line: usize,
+}
+
+impl Issue {
+    pub fn message(&self) -> String {
+        let other = self.other_line + 1;
+        match self.kind {
+            IssueKind::Duplicate => format!("Duplicato della riga {}", other),
+            IssueKind::Conflict => format!("Hostname mappato su altri IP (riga {})", other),
+            IssueKind::Shadowed => format!("Ignorato: vale la riga {}", other),
+            IssueKind::LocalhostCollision => "Ridefinisce localhost".to_string(),
+        }
+    }
+}
+
+// Il record localhost che l'applicazione scrive sempre in testa al file hosts
+pub fn is_protected_localhost(entry: &Entry) -> bool {
+    entry.ip == "127.0.0.1" && entry.hostname == "localhost"
+}
+
+// Controlla solo i record abilitati, gli unici che finiscono davvero nel file hosts.
+// Ritorna gli avvisi indicizzati per posizione nella lista di righe.
+pub fn find_issues(lines: &[Line]) -> HashMap<usize, Issue> {
+    let mut issues = HashMap::new();
+    // hostname -> indice della prima riga abilitata che lo definisce
+    let mut first_by_hostname: HashMap<&str, usize> = HashMap::new();
+    let mut first_by_mapping: HashMap<(&str, &str), usize> = HashMap::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let Line::Entry(entry) = line else { continue };
+        if !entry.enabled {
+            continue;
+        }
+
+        // 127.0.0.1 e ::1 per localhost convivono in ogni file hosts di serie: non sono conflitti
+        if entry.hostname == "localhost" {
+            if !is_protected_localhost(entry) && entry.ip != "::1" {
+                issues.insert(index, Issue { kind: IssueKind::LocalhostCollision, other_line: index });
+            }
+            continue;
+        }
+
+        if let Some(&first) = first_by_mapping.get(&(entry.ip.as_str(), entry.hostname.as_str())) {
+            issues.insert(index, Issue { kind: IssueKind::Duplicate, other_line: first });
+            continue;
+        }
+        first_by_mapping.insert((entry.ip.as_str(), entry.hostname.as_str()), index);
+
+        match first_by_hostname.get(entry.hostname.as_str()) {
+            Some(&first) => {
+                issues.insert(index, Issue { kind: IssueKind::Shadowed, other_line: first });
+                issues.entry(first).or_insert(Issue { kind: IssueKind::Conflict, other_line: index });
+            }
+            None => {
+                first_by_hostname.insert(entry.hostname.as_str(), index);
+            }
+        }
+    }
+    issues
+}
+
+// Correzione automatica: elimina i duplicati esatti e disabilita le righe ignorate dal sistema
+// o che ridefiniscono localhost, così restano visibili per una verifica manuale.
+// Ritorna il numero di righe modificate.
+pub fn fix_duplicates(lines: &mut Vec<Line>) -> usize {
+    let issues = find_issues(lines);
+    let mut changed = 0;
+
+    for (index, issue) in &issues {
+        if matches!(issue.kind, IssueKind::Shadowed | IssueKind::LocalhostCollision)
+            && let Some(Line::Entry(entry)) = lines.get_mut(*index) {
+            entry.enabled = false;
+            changed += 1;
+        }
+    }
+
+    let mut index = 0;
+    lines.retain(|_| {
+        let keep = !matches!(issues.get(&index), Some(issue) if issue.kind == IssueKind::Duplicate);
+        index += 1;
+        if !keep {
+            changed += 1;
+        }
+        keep
+    });
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ip: &str, hostname: &str, enabled: bool) -> Line {
+        Line::Entry(Entry { ip: ip.to_string(), hostname: hostname.to_string(), enabled, comment: None })
+    }
+
+    fn sample() -> Vec<Line> {
+        vec![
+            entry("10.0.0.1", "app.local", true),
+            entry("10.0.0.1", "app.local", true),
+            entry("10.0.0.2", "app.local", true),
+            entry("::1", "localhost", true),
+            entry("10.0.0.3", "localhost", true),
+            entry("10.0.0.9", "app.local", false),
+            entry("127.0.0.1", "localhost", true),
+        ]
+    }
+
+    fn issue(kind: IssueKind, other_line: usize) -> Issue {
+        Issue { kind, other_line }
+    }
+
+    #[test]
+    fn classifies_duplicates_conflicts_and_shadowed_lines() {
+        let issues = find_issues(&sample());
+        assert_eq!(issues.get(&0), Some(&issue(IssueKind::Conflict, 2)));
+        assert_eq!(issues.get(&1), Some(&issue(IssueKind::Duplicate, 0)));
+        assert_eq!(issues.get(&2), Some(&issue(IssueKind::Shadowed, 0)));
+        assert_eq!(issues.get(&4), Some(&issue(IssueKind::LocalhostCollision, 4)));
+        assert_eq!(issues.len(), 4);
+    }
+
+    #[test]
+    fn ipv6_localhost_and_disabled_lines_are_ignored() {
+        let issues = find_issues(&sample());
+        assert!(!issues.contains_key(&3));
+        assert!(!issues.contains_key(&5));
+        assert!(!issues.contains_key(&6));
+    }
+
+    #[test]
+    fn fix_duplicates_removes_and_disables_the_right_lines() {
+        let mut lines = sample();
+        assert_eq!(fix_duplicates(&mut lines), 3);
+        assert_eq!(lines, vec![
+            entry("10.0.0.1", "app.local", true),
+            entry("10.0.0.2", "app.local", false),
+            entry("::1", "localhost", true),
+            entry("10.0.0.3", "localhost", false),
+            entry("10.0.0.9", "app.local", false),
+            entry("127.0.0.1", "localhost", true),
+        ]);
+        assert!(find_issues(&lines).is_empty());
+    }
+}